@@ -0,0 +1,196 @@
+use crate::game::{PlaceOutcome, Spin};
+use crate::scoring::Score;
+
+/// The garbage lines sent by each placement in versus play.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AttackTable {
+    TetrisFriends,
+    /// TETR.IO's attack table, where combos multiply the lines sent and long back-to-back chains
+    /// send more lines.
+    TetrIO,
+    /// The Puyo Puyo Tetris guideline attack table.
+    PPT,
+}
+
+impl AttackTable {
+    /**
+    The lines sent by a placement with `outcome`, where `score` tracks the combo and back-to-back
+    chain before the placement.
+
+    All-spins send as many lines as T-spin minis.
+    */
+    pub fn get_attack(&self, outcome: &PlaceOutcome, score: &Score) -> u32 {
+        let lines_cleared = outcome.lines_cleared();
+        if lines_cleared == 0 {
+            return 0;
+        }
+
+        let is_difficult = lines_cleared >= 4 || outcome.spin.is_some();
+        let back_to_back_bonus = if is_difficult && score.back_to_back > 0 {
+            self.get_back_to_back_bonus(score.back_to_back)
+        } else {
+            0
+        };
+        let attack = get_clear_attack(lines_cleared, outcome.spin) + back_to_back_bonus;
+        let perfect_clear_attack = if outcome.is_perfect_clear { 10 } else { 0 };
+
+        self.with_combo(attack, score.combo) + perfect_clear_attack
+    }
+
+    /// The bonus for a difficult clear continuing a chain of `back_to_back` difficult clears.
+    fn get_back_to_back_bonus(&self, back_to_back: u32) -> u32 {
+        match self {
+            AttackTable::TetrisFriends | AttackTable::PPT => 1,
+            AttackTable::TetrIO => match back_to_back {
+                0..=2 => 1,
+                3..=7 => 2,
+                8..=23 => 3,
+                24..=66 => 4,
+                _ => 5,
+            },
+        }
+    }
+
+    /// The lines sent by `attack` after `combo` line clears in a row.
+    fn with_combo(&self, attack: u32, combo: u32) -> u32 {
+        let combo_table: &[u32] = match self {
+            AttackTable::TetrisFriends => &[0, 1, 1, 2, 2, 3, 3, 4, 4, 4, 5],
+            AttackTable::PPT => &[0, 0, 1, 1, 1, 2, 2, 3, 3, 4, 4, 4, 5],
+            AttackTable::TetrIO => {
+                let combo = combo as f32;
+                if attack == 0 {
+                    return (1.0 + 1.25 * combo).ln() as u32;
+                }
+                return (attack as f32 * (1.0 + 0.25 * combo)) as u32;
+            }
+        };
+        let combo_idx = (combo as usize).min(combo_table.len() - 1);
+        attack + combo_table[combo_idx]
+    }
+}
+
+fn get_clear_attack(lines_cleared: usize, spin: Option<Spin>) -> u32 {
+    match (spin, lines_cleared) {
+        (None, 0 | 1) => 0,
+        (None, 2) => 1,
+        (None, 3) => 2,
+        (None, _) => 4,
+        (Some(Spin::TSpin), 0) => 0,
+        (Some(Spin::TSpin), 1) => 2,
+        (Some(Spin::TSpin), 2) => 4,
+        (Some(Spin::TSpin), _) => 6,
+        (Some(Spin::TSpinMini | Spin::AllSpin), 0 | 1) => 0,
+        (Some(Spin::TSpinMini | Spin::AllSpin), _) => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TABLES: [AttackTable; 3] = [
+        AttackTable::TetrisFriends,
+        AttackTable::TetrIO,
+        AttackTable::PPT,
+    ];
+
+    fn outcome(lines_cleared: isize, spin: Option<Spin>) -> PlaceOutcome {
+        PlaceOutcome {
+            cleared_rows: (0..lines_cleared).collect(),
+            is_perfect_clear: false,
+            spin,
+        }
+    }
+
+    mod get_attack {
+        use super::*;
+
+        #[test]
+        fn line_clears() {
+            for table in TABLES {
+                for (lines_cleared, attack) in [(0, 0), (1, 0), (2, 1), (3, 2), (4, 4)] {
+                    assert_eq!(
+                        table.get_attack(&outcome(lines_cleared, None), &Score::initial()),
+                        attack
+                    );
+                }
+            }
+        }
+
+        #[test]
+        fn spins() {
+            for table in TABLES {
+                for (lines_cleared, spin, attack) in [
+                    (1, Spin::TSpin, 2),
+                    (2, Spin::TSpin, 4),
+                    (3, Spin::TSpin, 6),
+                    (1, Spin::TSpinMini, 0),
+                    (2, Spin::TSpinMini, 1),
+                    (2, Spin::AllSpin, 1),
+                ] {
+                    assert_eq!(
+                        table.get_attack(&outcome(lines_cleared, Some(spin)), &Score::initial()),
+                        attack
+                    );
+                }
+            }
+        }
+
+        #[test]
+        fn back_to_back() {
+            let score = Score {
+                back_to_back: 1,
+                ..Score::initial()
+            };
+
+            for table in TABLES {
+                assert_eq!(table.get_attack(&outcome(4, None), &score), 5);
+                assert_eq!(table.get_attack(&outcome(2, None), &score), 1);
+            }
+        }
+
+        #[test]
+        fn tetr_io_long_back_to_back() {
+            let score = Score {
+                back_to_back: 8,
+                ..Score::initial()
+            };
+
+            assert_eq!(AttackTable::TetrIO.get_attack(&outcome(4, None), &score), 7);
+            assert_eq!(AttackTable::PPT.get_attack(&outcome(4, None), &score), 5);
+        }
+
+        #[test]
+        fn combo() {
+            let score = Score {
+                combo: 4,
+                ..Score::initial()
+            };
+
+            assert_eq!(
+                AttackTable::TetrisFriends.get_attack(&outcome(1, None), &score),
+                2
+            );
+            assert_eq!(AttackTable::PPT.get_attack(&outcome(1, None), &score), 1);
+            // ln(1 + 1.25 * 4)
+            assert_eq!(AttackTable::TetrIO.get_attack(&outcome(1, None), &score), 1);
+            // 4 * (1 + 0.25 * 4)
+            assert_eq!(AttackTable::TetrIO.get_attack(&outcome(4, None), &score), 8);
+        }
+
+        #[test]
+        fn perfect_clear() {
+            for table in TABLES {
+                let attack = table.get_attack(
+                    &PlaceOutcome {
+                        is_perfect_clear: true,
+                        ..outcome(2, None)
+                    },
+                    &Score::initial(),
+                );
+
+                assert_eq!(attack, 1 + 10);
+            }
+        }
+    }
+}
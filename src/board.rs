@@ -1,3 +1,4 @@
+use crate::piece::PieceMask;
 use crate::utils::point::Point;
 use std::fmt::{self, Write};
 use wasm_bindgen::prelude::*;
@@ -11,16 +12,14 @@ pub struct Board {
     ///
     /// The segments are ordered from bottom to top and the cells in each segment are ordered from
     /// bottom-left to top-right.
-    ///
-    /// For perfect clears, we only need to check the bottom 4 lines plus 2 for piece spawn.
-    fill: u64,
+    fill: [u64; 4],
 }
 
 impl fmt::Debug for Board {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        for y in (0..6).rev() {
+        for y in (0..Board::HEIGHT).rev() {
             f.write_str(&format!("\n{:0>2} ", y))?;
-            for x in 0..10 {
+            for x in 0..Board::WIDTH {
                 f.write_char(if self.is_filled(&Point::new(x, y)) {
                     '■'
                 } else {
@@ -34,21 +33,37 @@ impl fmt::Debug for Board {
 
 #[wasm_bindgen]
 impl Board {
-    pub fn js_new(fill: u64) -> Board {
+    /// Create a board from up to 4 segment fills, ordered from bottom to top.
+    pub fn js_new(segment_fills: Vec<u64>) -> Board {
+        let mut fill = [0; 4];
+        for (segment, segment_fill) in fill.iter_mut().zip(segment_fills) {
+            *segment = segment_fill & Board::SEGMENT_FILL;
+        }
         Board { fill }
     }
 }
 
 impl Board {
+    pub const WIDTH: isize = 10;
+
+    /// The 20 visible rows and 4 buffer rows above them.
+    pub const HEIGHT: isize = 24;
+
+    pub const VISIBLE_HEIGHT: isize = 20;
+
+    const SEGMENT_HEIGHT: isize = 6;
+
+    const SEGMENT_FILL: u64 = (1 << 60) - 1;
+
+    const LINE_FILL: u64 = 0b1111111111;
+
     pub fn empty_board() -> Board {
-        Board {
-            fill: 0b0000000000_0000000000_0000000000_0000000000_0000000000_0000000000,
-        }
+        Board { fill: [0; 4] }
     }
 
     pub fn filled_board() -> Board {
         Board {
-            fill: 0b1111111111_1111111111_1111111111_1111111111_1111111111_1111111111,
+            fill: [Board::SEGMENT_FILL; 4],
         }
     }
 
@@ -60,66 +75,104 @@ impl Board {
         0b0000000000_0000000000_0000000000_1111111111_1111111111_1111111111;
     pub const FOUR_PC_FILL: u64 =
         0b0000000000_0000000000_1111111111_1111111111_1111111111_1111111111;
-    pub const PC_FILLS: [u64; 4] = [
+    pub const FIVE_PC_FILL: u64 =
+        0b0000000000_1111111111_1111111111_1111111111_1111111111_1111111111;
+    pub const SIX_PC_FILL: u64 =
+        0b1111111111_1111111111_1111111111_1111111111_1111111111_1111111111;
+    /// The fills of the bottom segment for perfect clears of 1 to 6 lines.
+    pub const PC_FILLS: [u64; 6] = [
         Board::ONE_PC_FILL,
         Board::TWO_PC_FILL,
         Board::THREE_PC_FILL,
         Board::FOUR_PC_FILL,
+        Board::FIVE_PC_FILL,
+        Board::SIX_PC_FILL,
     ];
-    pub const PC_BOARDS: [Board; 4] = [
+    pub const PC_BOARDS: [Board; 6] = [
         Board {
-            fill: Board::ONE_PC_FILL,
+            fill: [Board::ONE_PC_FILL, 0, 0, 0],
         },
         Board {
-            fill: Board::TWO_PC_FILL,
+            fill: [Board::TWO_PC_FILL, 0, 0, 0],
         },
         Board {
-            fill: Board::THREE_PC_FILL,
+            fill: [Board::THREE_PC_FILL, 0, 0, 0],
         },
         Board {
-            fill: Board::FOUR_PC_FILL,
+            fill: [Board::FOUR_PC_FILL, 0, 0, 0],
+        },
+        Board {
+            fill: [Board::FIVE_PC_FILL, 0, 0, 0],
+        },
+        Board {
+            fill: [Board::SIX_PC_FILL, 0, 0, 0],
         },
     ];
 
+    /// The segment holding line `y`, and the offset of the line's first cell in the segment.
+    fn line_location(y: isize) -> (usize, isize) {
+        (
+            (y / Board::SEGMENT_HEIGHT) as usize,
+            (y % Board::SEGMENT_HEIGHT) * Board::WIDTH,
+        )
+    }
+
+    /// The fill of line `y` as a 10 bit bitfield, ordered from left to right.
+    fn line_fill(&self, y: isize) -> u64 {
+        let (segment, offset) = Board::line_location(y);
+        (self.fill[segment] >> offset) & Board::LINE_FILL
+    }
+
+    fn set_line_fill(&mut self, y: isize, line_fill: u64) {
+        let (segment, offset) = Board::line_location(y);
+        self.fill[segment] &= !(Board::LINE_FILL << offset);
+        self.fill[segment] |= (line_fill & Board::LINE_FILL) << offset;
+    }
+
     /**
     `{ x: 0, y: 0 }` starts on the bottom-left.
 
     For convenience, we treat `x: -1` and `x: 10` as filled for the kick-table.
     */
     pub fn is_filled(&self, at: &Point) -> bool {
-        if at.x < 0 || at.x >= 10 || at.y < 0 {
+        if at.x < 0 || at.x >= Board::WIDTH || at.y < 0 {
             return true;
         }
-        if at.y >= 6 {
+        if at.y >= Board::HEIGHT {
             return false;
         }
-        (self.fill >> at.x + at.y * 10) & 0b1 == 1
+        (self.line_fill(at.y) >> at.x) & 0b1 == 1
     }
 
     pub fn fill(&mut self, point: &Point) {
-        if point.x < 0 || point.x >= 10 || point.y < 0 || point.y >= 6 {
+        if point.x < 0 || point.x >= Board::WIDTH || point.y < 0 || point.y >= Board::HEIGHT {
             return;
         }
-        self.fill |= 0b1 << (point.x + point.y * 10);
+        self.set_line_fill(point.y, self.line_fill(point.y) | 0b1 << point.x);
     }
 
     pub fn empty(&mut self, point: &Point) {
-        if point.x < 0 || point.x >= 10 || point.y < 0 || point.y >= 6 {
+        if point.x < 0 || point.x >= Board::WIDTH || point.y < 0 || point.y >= Board::HEIGHT {
             return;
         }
-        self.fill &= !(0b1 << (point.x + point.y * 10));
+        self.set_line_fill(point.y, self.line_fill(point.y) & !(0b1 << point.x));
     }
 
     pub fn is_empty_board(&self) -> bool {
-        self.fill == 0b0000000000_0000000000_0000000000_0000000000_0000000000_0000000000
+        self.fill.iter().all(|&segment_fill| segment_fill == 0)
     }
 
     pub fn has_intersect(&self, other: &Board) -> bool {
-        self.fill & other.fill > 0
+        self.fill
+            .iter()
+            .zip(other.fill.iter())
+            .any(|(a, b)| a & b > 0)
     }
 
     pub fn union(&mut self, other: &Board) {
-        self.fill |= other.fill;
+        for (segment_fill, other_fill) in self.fill.iter_mut().zip(other.fill.iter()) {
+            *segment_fill |= other_fill;
+        }
     }
 
     pub fn can_fit(&self, piece_points: &[Point; 4]) -> bool {
@@ -139,34 +192,172 @@ impl Board {
         }
     }
 
+    /// Whether the piece overlaps a filled cell or wall on line `y`, where `line_mask` is the
+    /// piece's cells on that line shifted by `x`.
+    fn is_line_mask_blocked(&self, y: isize, line_mask: u16, x: isize) -> bool {
+        if line_mask == 0 {
+            return false;
+        }
+        if y < 0 {
+            return true;
+        }
+        // Pad the line with 4 wall cells on either side so that the mask can be shifted into it.
+        let padded_x = x + 4;
+        if padded_x < 0 {
+            return true;
+        }
+        let walls = 0b1111 | (!0 << (Board::WIDTH + 4));
+        let line_fill = if y < Board::HEIGHT {
+            self.line_fill(y)
+        } else {
+            0
+        };
+        ((line_mask as u64) << padded_x) & (walls | line_fill << 4) != 0
+    }
+
+    pub fn can_fit_mask(&self, mask: &PieceMask) -> bool {
+        mask.lines.iter().zip(0..).all(|(&line_mask, dy)| {
+            !self.is_line_mask_blocked(mask.position.y + dy, line_mask, mask.position.x)
+        })
+    }
+
+    /// Whether a piece which fits on the board rests on a filled cell or the floor.
+    pub fn can_place_mask(&self, mask: &PieceMask) -> bool {
+        !self.can_fit_mask(&mask.moved(Point::new(0, -1)))
+    }
+
+    pub fn fill_mask(&mut self, mask: &PieceMask) {
+        for (&line_mask, dy) in mask.lines.iter().zip(0..) {
+            let y = mask.position.y + dy;
+            if line_mask == 0 || !(0..Board::HEIGHT).contains(&y) {
+                continue;
+            }
+            let shifted_mask = if mask.position.x < 0 {
+                (line_mask as u64) >> -mask.position.x
+            } else {
+                (line_mask as u64) << mask.position.x
+            };
+            self.set_line_fill(y, self.line_fill(y) | shifted_mask);
+        }
+    }
+
     pub fn is_line_filled(&self, y: isize) -> bool {
-        (0..10).all(|x| self.is_filled(&Point::new(x, y)))
+        if y < 0 {
+            return true;
+        }
+        y < Board::HEIGHT && self.line_fill(y) == Board::LINE_FILL
     }
 
     pub fn is_line_empty(&self, y: isize) -> bool {
-        (0..10).all(|x| !self.is_filled(&Point::new(x, y)))
+        if y < 0 {
+            return false;
+        }
+        y >= Board::HEIGHT || self.line_fill(y) == 0
     }
 
+    /// Whether every line up to the highest filled cell is filled, and there is at least one.
     pub fn can_perfect_clear(&self) -> bool {
-        Board::PC_FILLS.iter().any(|&fill| self.fill == fill)
+        let stack_height = self.stack_height();
+        stack_height > 0 && (0..stack_height).all(|y| self.is_line_filled(y))
     }
 
     pub fn clear_filled_lines(&mut self) {
         let mut next_board = Board::empty_board();
         let mut next_y = 0;
-        for y in 0..6 {
+        for y in 0..Board::HEIGHT {
             if self.is_line_filled(y) {
                 continue;
             }
-            for x in 0..10 {
-                if self.is_filled(&Point::new(x, y)) {
-                    next_board.fill(&Point::new(x, next_y));
-                }
-            }
+            next_board.set_line_fill(next_y, self.line_fill(y));
             next_y += 1;
         }
         self.fill = next_board.fill;
     }
+
+    /// Push `line_count` garbage lines onto the bottom of the board, each filled except for
+    /// `hole_column`. The stack shifts up, and cells shifted above the top of the board are lost.
+    pub fn push_garbage(&mut self, line_count: u8, hole_column: isize) {
+        let line_count = line_count as isize;
+        let mut next_board = Board::empty_board();
+        for y in 0..Board::HEIGHT {
+            if y < line_count {
+                next_board.set_line_fill(y, Board::LINE_FILL);
+                next_board.empty(&Point::new(hole_column, y));
+            } else {
+                next_board.set_line_fill(y, self.line_fill(y - line_count));
+            }
+        }
+        self.fill = next_board.fill;
+    }
+
+    /// The number of lines from the floor up to and including the highest filled cell.
+    pub fn stack_height(&self) -> isize {
+        (0..Board::HEIGHT)
+            .rev()
+            .find(|&y| !self.is_line_empty(y))
+            .map_or(0, |y| y + 1)
+    }
+
+    /// The number of empty cells in the bottom `height` lines.
+    pub fn empty_cell_count(&self, height: isize) -> usize {
+        (0..height.min(Board::HEIGHT))
+            .map(|y| (Board::WIDTH as u32 - self.line_fill(y).count_ones()) as usize)
+            .sum()
+    }
+
+    /// Whether the empty cells in the bottom `height` lines could be filled exactly by pieces of 4
+    /// cells.
+    pub fn has_fillable_empty_cell_count(&self, height: isize) -> bool {
+        self.empty_cell_count(height).is_multiple_of(4)
+    }
+
+    /// The number of empty cells in the bottom `height` lines of each group of columns between
+    /// columns which are filled in those lines.
+    pub fn empty_cell_counts_between_filled_columns(&self, height: isize) -> Vec<usize> {
+        let height = height.min(Board::HEIGHT);
+        let filled_columns = (0..height).fold(Board::LINE_FILL, |filled_columns, y| {
+            filled_columns & self.line_fill(y)
+        });
+        let mut counts = vec![];
+        let mut group = 0;
+        for x in 0..=Board::WIDTH {
+            if x < Board::WIDTH && (filled_columns >> x) & 0b1 == 0 {
+                group |= 0b1 << x;
+                continue;
+            }
+            if group != 0 {
+                let count = (0..height)
+                    .map(|y| (group & !self.line_fill(y)).count_ones() as usize)
+                    .sum();
+                counts.push(count);
+            }
+            group = 0;
+        }
+        counts
+    }
+
+    /// Whether every group of columns between filled columns could be filled exactly by pieces of
+    /// 4 cells. Pieces below `height` can never cross a filled column, even after lines are
+    /// cleared, so each group has to be filled on its own.
+    pub fn has_fillable_column_groups(&self, height: isize) -> bool {
+        self.empty_cell_counts_between_filled_columns(height)
+            .iter()
+            .all(|count| count.is_multiple_of(4))
+    }
+
+    /**
+    Whether the bottom `height` lines could still become a perfect clear with at most
+    `piece_count` more pieces.
+
+    Pieces are assumed to stay within the bottom `height` lines. Holes which are closed off from
+    the top line are not ruled out, since clearing a line above them can open them up again.
+    */
+    pub fn can_perfect_clear_within(&self, height: isize, piece_count: usize) -> bool {
+        self.stack_height() <= height
+            && self.empty_cell_count(height) <= piece_count * 4
+            && self.has_fillable_empty_cell_count(height)
+            && self.has_fillable_column_groups(height)
+    }
 }
 
 #[cfg(test)]
@@ -181,7 +372,7 @@ mod tests {
 
     fn assert_only_filled(board: &Board, fills: Vec<Point>) {
         for x in 0..10 {
-            for y in 0..6 {
+            for y in 0..Board::HEIGHT {
                 let is_filled = fills.contains(&Point::new(x, y));
                 assert_eq!(
                     board.is_filled(&Point::new(x, y)),
@@ -197,7 +388,7 @@ mod tests {
 
     fn assert_only_emptied(board: &Board, empties: Vec<Point>) {
         for x in 0..10 {
-            for y in 0..6 {
+            for y in 0..Board::HEIGHT {
                 let is_empty = empties.contains(&Point::new(x, y));
                 assert_eq!(
                     !board.is_filled(&Point::new(x, y)),
@@ -217,7 +408,12 @@ mod tests {
         #[test]
         fn detects_filled_and_empty_cells() {
             let board = Board {
-                fill: 0b0000000000_0000000000_0000000000_0000000001_1100000001_1101111011,
+                fill: [
+                    0b0000000000_0000000000_0000000000_0000000001_1100000001_1101111011,
+                    0,
+                    0,
+                    0,
+                ],
             };
 
             assert_only_filled(
@@ -243,7 +439,7 @@ mod tests {
         fn walls_are_filled() {
             let board = Board::empty_board();
 
-            for y in 0..6 {
+            for y in 0..Board::HEIGHT {
                 assert!(
                     board.is_filled(&Point::new(-1, y)),
                     "Expected left wall to be filled on line {}",
@@ -257,6 +453,15 @@ mod tests {
             }
         }
 
+        #[test]
+        fn above_board_is_empty() {
+            let board = Board::filled_board();
+
+            for x in 0..10 {
+                assert!(!board.is_filled(&Point::new(x, Board::HEIGHT)));
+            }
+        }
+
         #[test]
         fn floor_is_filled() {
             let board = Board::empty_board();
@@ -369,10 +574,20 @@ mod tests {
         #[test]
         fn interlaced_boards() {
             let a = Board {
-                fill: 0b0101010101_0101010101_0101010101_0101010101_0101010101_0101010101,
+                fill: [
+                    0b0101010101_0101010101_0101010101_0101010101_0101010101_0101010101,
+                    0,
+                    0,
+                    0,
+                ],
             };
             let b = Board {
-                fill: 0b1010101010_1010101010_1010101010_1010101010_1010101010_1010101010,
+                fill: [
+                    0b1010101010_1010101010_1010101010_1010101010_1010101010_1010101010,
+                    0,
+                    0,
+                    0,
+                ],
             };
             assert!(
                 !a.has_intersect(&b),
@@ -383,10 +598,20 @@ mod tests {
         #[test]
         fn overlap_on_bottom_left_cell() {
             let a = Board {
-                fill: 0b0101010101_0101010101_0101010101_0101010101_0101010101_0101010101,
+                fill: [
+                    0b0101010101_0101010101_0101010101_0101010101_0101010101_0101010101,
+                    0,
+                    0,
+                    0,
+                ],
             };
             let b = Board {
-                fill: 0b1010101010_1010101010_1010101010_1010101010_1010101010_1010101011,
+                fill: [
+                    0b1010101010_1010101010_1010101010_1010101010_1010101010_1010101011,
+                    0,
+                    0,
+                    0,
+                ],
             };
             assert!(a.has_intersect(&b), "Expected boards to overlap");
         }
@@ -398,17 +623,32 @@ mod tests {
         #[test]
         fn unions_another_board() {
             let mut a = Board {
-                fill: 0b0000000000_0000000000_0000000000_0000000000_0000000000_0000000000,
+                fill: [
+                    0b0000000000_0000000000_0000000000_0000000000_0000000000_0000000000,
+                    0,
+                    0,
+                    0,
+                ],
             };
 
             let b = Board {
-                fill: 0b0101010101_0101010101_0101010101_0101010101_0101010101_0101010101,
+                fill: [
+                    0b0101010101_0101010101_0101010101_0101010101_0101010101_0101010101,
+                    0,
+                    0,
+                    0,
+                ],
             };
 
             a.union(&b);
 
             let expected = Board {
-                fill: 0b0101010101_0101010101_0101010101_0101010101_0101010101_0101010101,
+                fill: [
+                    0b0101010101_0101010101_0101010101_0101010101_0101010101_0101010101,
+                    0,
+                    0,
+                    0,
+                ],
             };
 
             assert_eq!(expected, a);
@@ -421,7 +661,12 @@ mod tests {
         #[test]
         fn fits_in_a_minimal_gap() {
             let board = Board {
-                fill: 0b1110000111_1111111111_1111111111_1111111111_1111111111_1111111111,
+                fill: [
+                    0b1110000111_1111111111_1111111111_1111111111_1111111111_1111111111,
+                    0,
+                    0,
+                    0,
+                ],
             };
 
             let piece = Piece {
@@ -440,7 +685,12 @@ mod tests {
         #[test]
         fn cannot_fit_when_cell_overlaps() {
             let board = Board {
-                fill: 0b0001000000_0000000000_0000000000_0000000000_0000000000_0000000000,
+                fill: [
+                    0b0001000000_0000000000_0000000000_0000000000_0000000000_0000000000,
+                    0,
+                    0,
+                    0,
+                ],
             };
 
             let piece = Piece {
@@ -515,6 +765,172 @@ mod tests {
         }
     }
 
+    mod can_fit_mask {
+        use super::*;
+
+        #[test]
+        fn fits_in_a_minimal_gap() {
+            let board = Board {
+                fill: [
+                    0b1110000111_1111111111_1111111111_1111111111_1111111111_1111111111,
+                    0,
+                    0,
+                    0,
+                ],
+            };
+
+            let piece = Piece {
+                kind: PieceKind::I,
+                orientation: Orientation::North,
+                position: Point::new(3, 3),
+            };
+
+            assert!(board.can_fit_mask(&piece.get_mask(&CONFIG)));
+        }
+
+        #[test]
+        fn cannot_fit_when_cell_overlaps() {
+            let mut board = Board::empty_board();
+            board.fill(&Point::new(6, 20));
+
+            let piece = Piece::spawn(&CONFIG, &PieceKind::I);
+
+            assert!(!board.can_fit_mask(&piece.get_mask(&CONFIG)));
+        }
+
+        #[test]
+        fn cannot_fit_when_wall_collides() {
+            let board = Board::empty_board();
+
+            for x in [-1, 7] {
+                let piece = Piece {
+                    position: Point::new(x, 0),
+                    ..Piece::spawn(&CONFIG, &PieceKind::I)
+                };
+
+                assert!(!board.can_fit_mask(&piece.get_mask(&CONFIG)));
+            }
+        }
+
+        #[test]
+        fn fits_with_empty_bounding_box_column_outside_walls() {
+            let board = Board::empty_board();
+
+            let piece = Piece {
+                kind: PieceKind::I,
+                orientation: Orientation::East,
+                position: Point::new(-2, 0),
+            };
+
+            assert!(board.can_fit_mask(&piece.get_mask(&CONFIG)));
+        }
+
+        #[test]
+        fn agrees_with_points() {
+            let mut board = Board::empty_board();
+            for (x, y) in [(0, 0), (1, 0), (4, 1), (5, 2), (9, 3), (2, 21)] {
+                board.fill(&Point::new(x, y));
+            }
+
+            for kind in crate::piece::PIECE_KINDS {
+                for orientation in [
+                    Orientation::North,
+                    Orientation::South,
+                    Orientation::East,
+                    Orientation::West,
+                ] {
+                    for x in -3..11 {
+                        for y in -3..25 {
+                            let piece = Piece {
+                                kind,
+                                orientation,
+                                position: Point::new(x, y),
+                            };
+                            let points = piece.get_points(&CONFIG);
+                            let mask = piece.get_mask(&CONFIG);
+                            assert_eq!(
+                                board.can_fit_mask(&mask),
+                                board.can_fit(&points),
+                                "Expected {:?} to fit the same as its points",
+                                piece
+                            );
+                            if board.can_fit(&points) {
+                                assert_eq!(
+                                    board.can_place_mask(&mask),
+                                    board.can_place(&points),
+                                    "Expected {:?} to place the same as its points",
+                                    piece
+                                );
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    mod can_place_mask {
+        use super::*;
+
+        #[test]
+        fn can_place_i_piece_on_floor() {
+            let board = Board::empty_board();
+
+            let piece = Piece {
+                position: Point::new(3, -2),
+                ..Piece::spawn(&CONFIG, &PieceKind::I)
+            };
+
+            assert!(board.can_place_mask(&piece.get_mask(&CONFIG)));
+        }
+
+        #[test]
+        fn cannot_place_i_piece_in_air() {
+            let board = Board::empty_board();
+
+            let piece = Piece {
+                position: Point::new(3, -1),
+                ..Piece::spawn(&CONFIG, &PieceKind::I)
+            };
+
+            assert!(!board.can_place_mask(&piece.get_mask(&CONFIG)));
+        }
+
+        #[test]
+        fn can_place_j_piece_on_filled_cell() {
+            let mut board = Board::empty_board();
+            board.fill(&Point::new(0, 0));
+            board.fill(&Point::new(0, 1));
+
+            let piece = Piece {
+                position: Point::new(0, 1),
+                ..Piece::spawn(&CONFIG, &PieceKind::J)
+            };
+
+            assert!(board.can_place_mask(&piece.get_mask(&CONFIG)));
+        }
+    }
+
+    mod fill_mask {
+        use super::*;
+
+        #[test]
+        fn fills_piece() {
+            let mut board = Board::empty_board();
+            let piece = Piece {
+                kind: PieceKind::T,
+                orientation: Orientation::East,
+                position: Point::new(-1, 5),
+            };
+
+            board.fill_mask(&piece.get_mask(&CONFIG));
+
+            let mut expected_board = Board::empty_board();
+            expected_board.fill_piece_points(&piece.get_points(&CONFIG));
+            assert_eq!(board, expected_board);
+        }
+    }
+
     mod fill_piece_points {
         use super::*;
 
@@ -529,7 +945,12 @@ mod tests {
             board.fill_piece_points(&piece.get_points(&CONFIG));
 
             let expected_board = Board {
-                fill: 0b0001111000_0000000000_0000000000_0000000000_0000000000_0000000000,
+                fill: [
+                    0b0001111000_0000000000_0000000000_0000000000_0000000000_0000000000,
+                    0,
+                    0,
+                    0,
+                ],
             };
 
             assert_eq!(board, expected_board,)
@@ -542,7 +963,7 @@ mod tests {
         #[test]
         fn line_filled() {
             let board = Board::filled_board();
-            for y in 0..6 {
+            for y in 0..Board::HEIGHT {
                 assert!(board.is_line_filled(y));
             }
         }
@@ -550,7 +971,7 @@ mod tests {
         #[test]
         fn line_not_filled() {
             let board = Board::empty_board();
-            for y in 0..6 {
+            for y in 0..Board::HEIGHT {
                 assert!(!board.is_line_filled(y));
             }
         }
@@ -558,10 +979,10 @@ mod tests {
         #[test]
         fn line_not_filled_if_any_empty_cell() {
             let mut board = Board::filled_board();
-            for y in 0..6 {
+            for y in 0..Board::HEIGHT {
                 board.empty(&Point::new(5, y));
             }
-            for y in 0..6 {
+            for y in 0..Board::HEIGHT {
                 assert!(!board.is_line_filled(y));
             }
         }
@@ -573,7 +994,7 @@ mod tests {
         #[test]
         fn line_empty() {
             let board = Board::empty_board();
-            for y in 0..6 {
+            for y in 0..Board::HEIGHT {
                 assert!(board.is_line_empty(y));
             }
         }
@@ -581,7 +1002,7 @@ mod tests {
         #[test]
         fn line_not_empty() {
             let board = Board::filled_board();
-            for y in 0..6 {
+            for y in 0..Board::HEIGHT {
                 assert!(!board.is_line_empty(y));
             }
         }
@@ -589,10 +1010,10 @@ mod tests {
         #[test]
         fn line_not_empty_if_any_filled_cell() {
             let mut board = Board::empty_board();
-            for y in 0..6 {
+            for y in 0..Board::HEIGHT {
                 board.fill(&Point::new(5, y));
             }
-            for y in 0..6 {
+            for y in 0..Board::HEIGHT {
                 assert!(!board.is_line_empty(y));
             }
         }
@@ -607,6 +1028,22 @@ mod tests {
             assert!(board.can_perfect_clear());
         }
 
+        #[test]
+        fn can_perfect_clear_across_segments() {
+            let mut board = Board::empty_board();
+            for y in 0..8 {
+                for x in 0..10 {
+                    board.fill(&Point::new(x, y));
+                }
+            }
+            assert!(board.can_perfect_clear());
+        }
+
+        #[test]
+        fn cannot_perfect_clear_empty_board() {
+            assert!(!Board::empty_board().can_perfect_clear());
+        }
+
         #[test]
         fn cannot_perfect_clear_if_gap() {
             let mut board = Board::filled_board();
@@ -621,16 +1058,34 @@ mod tests {
         #[test]
         fn no_difference_if_no_filled_lines() {
             let mut board = Board::filled_board();
-            for y in 0..6 {
+            for y in 0..Board::HEIGHT {
                 board.empty(&Point::new(y % 10, y));
             }
 
-            let mut next_board = board.clone();
+            let mut next_board = board;
             next_board.clear_filled_lines();
 
             assert_eq!(next_board, board);
         }
 
+        #[test]
+        fn moves_lines_down_into_lower_segment() {
+            let mut board = Board::empty_board();
+            for x in 0..10 {
+                board.fill(&Point::new(x, 5));
+                board.fill(&Point::new(x, 6));
+            }
+            board.fill(&Point::new(2, 7));
+            board.fill(&Point::new(7, 21));
+
+            board.clear_filled_lines();
+
+            let mut expected_board = Board::empty_board();
+            expected_board.fill(&Point::new(2, 5));
+            expected_board.fill(&Point::new(7, 19));
+            assert_eq!(board, expected_board);
+        }
+
         #[test]
         fn moves_lines_down_when_clearing() {
             let board = {
@@ -650,7 +1105,7 @@ mod tests {
                 b
             };
 
-            let mut next_board = board.clone();
+            let mut next_board = board;
             next_board.clear_filled_lines();
 
             let expected_board = {
@@ -665,4 +1120,217 @@ mod tests {
             assert_eq!(next_board, expected_board);
         }
     }
+
+    mod push_garbage {
+        use super::*;
+
+        #[test]
+        fn fills_lines_except_hole_column() {
+            let mut board = Board::empty_board();
+
+            board.push_garbage(2, 3);
+
+            let mut expected_board = Board::empty_board();
+            for y in 0..2 {
+                for x in 0..10 {
+                    if x != 3 {
+                        expected_board.fill(&Point::new(x, y));
+                    }
+                }
+            }
+            assert_eq!(board, expected_board);
+        }
+
+        #[test]
+        fn shifts_stack_up_across_segments() {
+            let mut board = Board::empty_board();
+            board.fill(&Point::new(2, 0));
+            board.fill(&Point::new(7, 5));
+
+            board.push_garbage(1, 0);
+
+            assert!(!board.is_filled(&Point::new(0, 0)));
+            assert!(board.is_filled(&Point::new(1, 0)));
+            assert!(board.is_filled(&Point::new(2, 1)));
+            assert!(board.is_filled(&Point::new(7, 6)));
+            assert_eq!(board.stack_height(), 7);
+        }
+
+        #[test]
+        fn drops_cells_above_top() {
+            let mut board = Board::empty_board();
+            board.fill(&Point::new(4, Board::HEIGHT - 1));
+
+            board.push_garbage(1, 0);
+
+            assert_eq!(board.stack_height(), 1);
+        }
+
+        #[test]
+        fn fills_board_with_more_lines_than_height() {
+            let mut board = Board::empty_board();
+            board.fill(&Point::new(4, 0));
+
+            board.push_garbage(Board::HEIGHT as u8 + 1, 0);
+
+            assert_eq!(board.stack_height(), Board::HEIGHT);
+            assert!(!board.is_filled(&Point::new(0, Board::HEIGHT - 1)));
+            assert!(board.is_filled(&Point::new(4, Board::HEIGHT - 1)));
+        }
+    }
+
+    mod stack_height {
+        use super::*;
+
+        #[test]
+        fn zero_for_empty_board() {
+            assert_eq!(Board::empty_board().stack_height(), 0);
+        }
+
+        #[test]
+        fn highest_filled_cell() {
+            let mut board = Board::empty_board();
+            board.fill(&Point::new(0, 0));
+            board.fill(&Point::new(5, 2));
+
+            assert_eq!(board.stack_height(), 3);
+        }
+    }
+
+    mod empty_cell_count {
+        use super::*;
+
+        #[test]
+        fn counts_cells_below_height() {
+            let mut board = Board::empty_board();
+            board.fill(&Point::new(0, 0));
+            board.fill(&Point::new(0, 3));
+
+            assert_eq!(board.empty_cell_count(2), 19);
+            assert_eq!(board.empty_cell_count(4), 38);
+        }
+    }
+
+    mod has_fillable_empty_cell_count {
+        use super::*;
+
+        #[test]
+        fn fillable_if_multiple_of_four() {
+            let mut board = Board::empty_board();
+            for x in 0..6 {
+                board.fill(&Point::new(x, 0));
+            }
+
+            assert!(board.has_fillable_empty_cell_count(1));
+            assert!(!board.has_fillable_empty_cell_count(2));
+        }
+    }
+
+    mod has_fillable_column_groups {
+        use super::*;
+
+        #[test]
+        fn fillable_if_every_group_is_multiple_of_four() {
+            let mut board = Board::filled_board();
+            for y in 0..4 {
+                board.empty(&Point::new(0, y));
+                board.empty(&Point::new(9, y));
+            }
+
+            assert_eq!(
+                board.empty_cell_counts_between_filled_columns(4),
+                vec![4, 4]
+            );
+            assert!(board.has_fillable_column_groups(4));
+        }
+
+        #[test]
+        fn counts_groups_of_several_columns() {
+            let mut board = Board::empty_board();
+            for y in 0..4 {
+                board.fill(&Point::new(3, y));
+                board.fill(&Point::new(6, y));
+            }
+            board.fill(&Point::new(4, 0));
+            board.fill(&Point::new(9, 3));
+
+            assert_eq!(
+                board.empty_cell_counts_between_filled_columns(4),
+                vec![12, 7, 11]
+            );
+        }
+
+        #[test]
+        fn not_fillable_if_group_is_split_unevenly() {
+            let mut board = Board::empty_board();
+            for y in 0..4 {
+                board.fill(&Point::new(2, y));
+            }
+
+            assert_eq!(
+                board.empty_cell_counts_between_filled_columns(4),
+                vec![8, 28]
+            );
+            assert!(board.has_fillable_column_groups(4));
+
+            board.fill(&Point::new(0, 0));
+            board.fill(&Point::new(1, 0));
+            board.fill(&Point::new(8, 0));
+            board.fill(&Point::new(9, 0));
+            assert!(board.has_fillable_empty_cell_count(4));
+            assert!(!board.has_fillable_column_groups(4));
+        }
+    }
+
+    mod can_perfect_clear_within {
+        use super::*;
+
+        fn board_with_empty_column(x: isize) -> Board {
+            let mut board = Board::empty_board();
+            for y in 0..4 {
+                for other_x in (0..10).filter(|&other_x| other_x != x) {
+                    board.fill(&Point::new(other_x, y));
+                }
+            }
+            board
+        }
+
+        #[test]
+        fn possible_with_enough_pieces() {
+            let board = board_with_empty_column(9);
+
+            assert!(board.can_perfect_clear_within(4, 1));
+        }
+
+        #[test]
+        fn impossible_without_enough_pieces() {
+            let board = board_with_empty_column(9);
+
+            assert!(!board.can_perfect_clear_within(4, 0));
+        }
+
+        #[test]
+        fn impossible_if_stack_too_high() {
+            let board = board_with_empty_column(9);
+
+            assert!(!board.can_perfect_clear_within(2, 10));
+        }
+
+        #[test]
+        fn possible_with_hole_under_clearable_line() {
+            let mut board = Board::empty_board();
+            for x in 1..10 {
+                board.fill(&Point::new(x, 0));
+            }
+            for x in 0..7 {
+                board.fill(&Point::new(x, 1));
+            }
+            for x in 2..10 {
+                board.fill(&Point::new(x, 2));
+            }
+
+            // Clearing the second line opens the hole in the first line up again.
+            assert!(board.can_perfect_clear_within(4, 4));
+        }
+    }
 }
@@ -1,26 +1,175 @@
+use crate::attack::AttackTable;
 use crate::game::Move;
+use crate::kick_table::KickTable;
 use crate::piece::PieceKind;
+use crate::randomizer::{Bag, History, PieceHistory, PieceRandomizer, Random};
 use crate::utils::direction::Direction;
+use crate::utils::piece_kind_set::PieceKindSet;
 use crate::utils::point::Point;
 use crate::utils::rotation::{Orientation, Rotation};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Kick {
     SRS,
+    /// SRS with TETR.IO's symmetric I kicks.
+    SRSPlus,
+    /// SRS-X shares the SRS kicks for 90 rotations, and always allows 180 rotations with its own
+    /// wide 180 kicks, which are `HalfKick::PPT`'s. `Config::half_rotation` is ignored.
+    SRSX,
+    /// The Arika Rotation System used in Tetris The Grand Master. Pieces spawn flat side up and
+    /// rest on the bottom of their bounding box, and kick one column right then left, except for
+    /// the I and O pieces which never kick.
+    ///
+    /// The center column rule, which prevents some L, J and T kicks, is not modelled.
+    ARS,
+    /// Rotations only succeed if the piece fits without a kick.
+    NoKicks,
+    /// SRS piece orientations with kicks loaded from a user-defined table. `Config::half_rotation`
+    /// still decides whether 180 rotations are allowed, but their kicks also come from the table.
+    Custom(KickTable),
 }
 
-#[derive(Debug, Clone)]
+/// The kick table used for 180 rotations.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HalfKick {
+    /// 180 rotations only succeed if the piece fits without a kick.
+    NoKicks,
+    /// The 180 kicks used in TETR.IO's SRS+.
+    TetrIO,
+    /// The wider 180 kicks used in Puyo Puyo Tetris-style clients, which try horizontal kicks
+    /// before vertical ones. These are the SRS-X 180 kicks.
+    PPT,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Gravity {
+    /// Pieces only move down when dropped.
+    Zero,
+    /// Pieces sink to the lowest position they can reach as soon as they spawn or move.
+    TwentyG,
+}
+
+/// Which way to round the spawn column of pieces which cannot be centred on the board.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SpawnColumn {
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Spawn {
+    /// The line the lowest cells of a piece spawn on, counted up from the first line above the
+    /// visible field. Negative rows spawn inside the visible field.
+    pub row: isize,
+
+    pub column: SpawnColumn,
+
+    pub orientation: Orientation,
+
+    /// Whether pieces which collide with the stack where they spawn move up until they fit,
+    /// instead of topping out.
+    pub above_stack: bool,
+}
+
+impl Spawn {
+    pub const fn default() -> Spawn {
+        Spawn {
+            row: 0,
+            column: SpawnColumn::Left,
+            orientation: Orientation::North,
+            above_stack: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Randomizer {
+    Random,
+    SevenBag,
+    FourteenBag,
+    /// Rerolls recently dealt piece kinds up to `rolls` times.
+    History {
+        rolls: u8,
+    },
+}
+
+impl Randomizer {
+    /// The history randomizer used in Tetris The Grand Master.
+    pub const TGM: Randomizer = Randomizer::History { rolls: 4 };
+
+    /// The history randomizer used in Tetris The Grand Master 2.
+    pub const TGM2: Randomizer = Randomizer::History { rolls: 6 };
+}
+
+impl PieceRandomizer for Randomizer {
+    fn next_piece_probabilities(&self, history: &PieceHistory) -> PieceKindSet<f32> {
+        match self {
+            Randomizer::Random => Random.next_piece_probabilities(history),
+            Randomizer::SevenBag => Bag { copies: 1 }.next_piece_probabilities(history),
+            Randomizer::FourteenBag => Bag { copies: 2 }.next_piece_probabilities(history),
+            Randomizer::History { rolls } => {
+                History { rolls: *rolls }.next_piece_probabilities(history)
+            }
+        }
+    }
+
+    fn with_seen_piece_kind(&self, history: &PieceHistory, kind: &PieceKind) -> PieceHistory {
+        match self {
+            Randomizer::Random => Random.with_seen_piece_kind(history, kind),
+            Randomizer::SevenBag => Bag { copies: 1 }.with_seen_piece_kind(history, kind),
+            Randomizer::FourteenBag => Bag { copies: 2 }.with_seen_piece_kind(history, kind),
+            Randomizer::History { rolls } => {
+                History { rolls: *rolls }.with_seen_piece_kind(history, kind)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Config {
     pub kick: Kick,
 
+    pub spawn: Spawn,
+
+    pub randomizer: Randomizer,
+
     pub soft_drop_allowed: bool,
+
+    pub gravity: Gravity,
+
+    /// The kick table for 180 rotations, or `None` if 180 rotations are not allowed. Ignored under
+    /// `Kick::SRSX`, which always uses its own 180 kicks.
+    pub half_rotation: Option<HalfKick>,
+
+    /// Whether a rotation can be held while a piece is waiting to spawn, so that it spawns
+    /// rotated.
+    pub initial_rotation: bool,
+
+    /// Whether hold can be used while a piece is waiting to spawn, so that the hold piece spawns
+    /// in its place.
+    pub initial_hold: bool,
+
+    /// The number of lines to perfect clear. The stack may not grow above this height, and
+    /// perfect clears of fewer lines are also accepted.
+    pub perfect_clear_height: isize,
+
+    /// The attack table used to rank perfect clears by the lines they send.
+    pub attack_table: AttackTable,
 }
 
 impl Config {
     pub const fn default() -> Config {
         Config {
             kick: Kick::SRS,
+            spawn: Spawn::default(),
+            randomizer: Randomizer::SevenBag,
             soft_drop_allowed: false,
+            gravity: Gravity::Zero,
+            half_rotation: None,
+            initial_rotation: false,
+            initial_hold: false,
+            perfect_clear_height: 4,
+            attack_table: AttackTable::PPT,
         }
     }
 
@@ -29,114 +178,90 @@ impl Config {
         piece_kind: &PieceKind,
         from: &Orientation,
         to: &Orientation,
-    ) -> Option<[Point; 4]> {
+    ) -> Option<Vec<Point>> {
+        if let Kick::Custom(table) = &self.kick {
+            return table.get(piece_kind, from, to).map(<[Point]>::to_vec);
+        }
+        if from.rotated(&Rotation::Half) == *to {
+            return self.half_kick_table(piece_kind, from);
+        }
+        match &self.kick {
+            Kick::SRS | Kick::SRSX => srs_kick_table(piece_kind, from, to),
+            Kick::SRSPlus => match piece_kind {
+                PieceKind::I => srs_plus_i_kick_table(from, to),
+                _ => srs_kick_table(piece_kind, from, to),
+            },
+            Kick::ARS => match piece_kind {
+                PieceKind::I | PieceKind::O => None,
+                _ => Some(vec![Point::new(1, 0), Point::new(-1, 0)]),
+            },
+            Kick::NoKicks | Kick::Custom(_) => None,
+        }
+    }
+
+    /// The kick table used for 180 rotations, or `None` if 180 rotations are not allowed.
+    pub fn half_kick(&self) -> Option<&HalfKick> {
         match self.kick {
-            Kick::SRS => match piece_kind {
+            Kick::SRSX => Some(&HalfKick::PPT),
+            _ => self.half_rotation.as_ref(),
+        }
+    }
+
+    fn half_kick_table(&self, piece_kind: &PieceKind, from: &Orientation) -> Option<Vec<Point>> {
+        match self.half_kick()? {
+            HalfKick::NoKicks => None,
+            HalfKick::TetrIO => match piece_kind {
                 PieceKind::O => None,
-                PieceKind::I => match (from, to) {
-                    (Orientation::North, Orientation::East) => Some([
-                        Point::new(-2, 0),
-                        Point::new(1, 0),
-                        Point::new(-2, -1),
-                        Point::new(1, 2),
-                    ]),
-                    (Orientation::East, Orientation::North) => Some([
-                        Point::new(2, 0),
-                        Point::new(-1, 0),
-                        Point::new(2, 1),
-                        Point::new(-1, -2),
-                    ]),
-                    (Orientation::East, Orientation::South) => Some([
-                        Point::new(-1, 0),
-                        Point::new(2, 0),
-                        Point::new(-1, 2),
-                        Point::new(2, -1),
-                    ]),
-                    (Orientation::South, Orientation::East) => Some([
-                        Point::new(1, 0),
-                        Point::new(-2, 0),
-                        Point::new(1, -2),
-                        Point::new(-2, 1),
-                    ]),
-                    (Orientation::South, Orientation::West) => Some([
-                        Point::new(2, 0),
-                        Point::new(-1, 0),
-                        Point::new(2, 1),
-                        Point::new(-1, -2),
-                    ]),
-                    (Orientation::West, Orientation::South) => Some([
-                        Point::new(-2, 0),
-                        Point::new(1, 0),
-                        Point::new(-2, -1),
-                        Point::new(1, 2),
-                    ]),
-                    (Orientation::West, Orientation::North) => Some([
+                _ => match from {
+                    Orientation::North => Some(vec![
+                        Point::new(0, 1),
+                        Point::new(1, 1),
+                        Point::new(-1, 1),
                         Point::new(1, 0),
-                        Point::new(-2, 0),
-                        Point::new(1, -2),
-                        Point::new(-2, 1),
-                    ]),
-                    (Orientation::North, Orientation::West) => Some([
                         Point::new(-1, 0),
-                        Point::new(2, 0),
-                        Point::new(-1, 2),
-                        Point::new(2, -1),
                     ]),
-                    _ => None,
-                },
-                _ => match (from, to) {
-                    (Orientation::North, Orientation::East) => Some([
+                    Orientation::South => Some(vec![
+                        Point::new(0, -1),
+                        Point::new(-1, -1),
+                        Point::new(1, -1),
                         Point::new(-1, 0),
-                        Point::new(-1, 1),
-                        Point::new(0, -2),
-                        Point::new(-1, -2),
-                    ]),
-                    (Orientation::East, Orientation::North) => Some([
                         Point::new(1, 0),
-                        Point::new(1, -1),
-                        Point::new(0, 2),
-                        Point::new(1, 2),
                     ]),
-                    (Orientation::East, Orientation::South) => Some([
+                    Orientation::East => Some(vec![
                         Point::new(1, 0),
-                        Point::new(1, -1),
-                        Point::new(0, 2),
                         Point::new(1, 2),
-                    ]),
-                    (Orientation::South, Orientation::East) => Some([
-                        Point::new(-1, 0),
-                        Point::new(-1, 1),
-                        Point::new(0, -2),
-                        Point::new(-1, -2),
-                    ]),
-                    (Orientation::South, Orientation::West) => Some([
-                        Point::new(1, 0),
                         Point::new(1, 1),
-                        Point::new(0, -2),
-                        Point::new(1, -2),
-                    ]),
-                    (Orientation::West, Orientation::South) => Some([
-                        Point::new(-1, 0),
-                        Point::new(-1, -1),
                         Point::new(0, 2),
-                        Point::new(-1, 2),
+                        Point::new(0, 1),
                     ]),
-                    (Orientation::West, Orientation::North) => Some([
+                    Orientation::West => Some(vec![
                         Point::new(-1, 0),
-                        Point::new(-1, -1),
-                        Point::new(0, 2),
                         Point::new(-1, 2),
+                        Point::new(-1, 1),
+                        Point::new(0, 2),
+                        Point::new(0, 1),
                     ]),
-                    (Orientation::North, Orientation::West) => Some([
-                        Point::new(1, 0),
-                        Point::new(1, 1),
-                        Point::new(0, -2),
-                        Point::new(1, -2),
-                    ]),
-                    _ => None,
                 },
             },
+            HalfKick::PPT => srs_x_half_kick_table(piece_kind, from),
+        }
+    }
+
+    /// Whether pieces can rotate 180 degrees.
+    pub fn is_half_rotation_allowed(&self) -> bool {
+        self.half_kick().is_some()
+    }
+
+    /// The rotations which can be held while a piece is waiting to spawn.
+    pub fn possible_initial_rotations(&self) -> Vec<Rotation> {
+        if !self.initial_rotation {
+            return vec![];
+        }
+        let mut rotations = vec![Rotation::Clockwise, Rotation::AntiClockwise];
+        if self.is_half_rotation_allowed() {
+            rotations.push(Rotation::Half);
         }
+        rotations
     }
 
     pub fn possible_moves(&self) -> Vec<Move> {
@@ -147,9 +272,274 @@ impl Config {
             Move::Translate(Direction::Left),
             Move::Translate(Direction::Right),
         ];
+        if self.is_half_rotation_allowed() {
+            moves.push(Move::Rotate(Rotation::Half));
+        }
         if self.soft_drop_allowed {
             moves.push(Move::Translate(Direction::Down));
         }
+        // Pieces are always as low as they can go, so moving down does nothing.
+        if self.gravity == Gravity::TwentyG {
+            moves.retain(|&mov| mov != Move::Drop && mov != Move::Translate(Direction::Down));
+        }
         moves
     }
 }
+
+fn srs_kick_table(
+    piece_kind: &PieceKind,
+    from: &Orientation,
+    to: &Orientation,
+) -> Option<Vec<Point>> {
+    match piece_kind {
+        PieceKind::O => None,
+        PieceKind::I => match (from, to) {
+            (Orientation::North, Orientation::East) => Some(vec![
+                Point::new(-2, 0),
+                Point::new(1, 0),
+                Point::new(-2, -1),
+                Point::new(1, 2),
+            ]),
+            (Orientation::East, Orientation::North) => Some(vec![
+                Point::new(2, 0),
+                Point::new(-1, 0),
+                Point::new(2, 1),
+                Point::new(-1, -2),
+            ]),
+            (Orientation::East, Orientation::South) => Some(vec![
+                Point::new(-1, 0),
+                Point::new(2, 0),
+                Point::new(-1, 2),
+                Point::new(2, -1),
+            ]),
+            (Orientation::South, Orientation::East) => Some(vec![
+                Point::new(1, 0),
+                Point::new(-2, 0),
+                Point::new(1, -2),
+                Point::new(-2, 1),
+            ]),
+            (Orientation::South, Orientation::West) => Some(vec![
+                Point::new(2, 0),
+                Point::new(-1, 0),
+                Point::new(2, 1),
+                Point::new(-1, -2),
+            ]),
+            (Orientation::West, Orientation::South) => Some(vec![
+                Point::new(-2, 0),
+                Point::new(1, 0),
+                Point::new(-2, -1),
+                Point::new(1, 2),
+            ]),
+            (Orientation::West, Orientation::North) => Some(vec![
+                Point::new(1, 0),
+                Point::new(-2, 0),
+                Point::new(1, -2),
+                Point::new(-2, 1),
+            ]),
+            (Orientation::North, Orientation::West) => Some(vec![
+                Point::new(-1, 0),
+                Point::new(2, 0),
+                Point::new(-1, 2),
+                Point::new(2, -1),
+            ]),
+            _ => None,
+        },
+        _ => match (from, to) {
+            (Orientation::North, Orientation::East) => Some(vec![
+                Point::new(-1, 0),
+                Point::new(-1, 1),
+                Point::new(0, -2),
+                Point::new(-1, -2),
+            ]),
+            (Orientation::East, Orientation::North) => Some(vec![
+                Point::new(1, 0),
+                Point::new(1, -1),
+                Point::new(0, 2),
+                Point::new(1, 2),
+            ]),
+            (Orientation::East, Orientation::South) => Some(vec![
+                Point::new(1, 0),
+                Point::new(1, -1),
+                Point::new(0, 2),
+                Point::new(1, 2),
+            ]),
+            (Orientation::South, Orientation::East) => Some(vec![
+                Point::new(-1, 0),
+                Point::new(-1, 1),
+                Point::new(0, -2),
+                Point::new(-1, -2),
+            ]),
+            (Orientation::South, Orientation::West) => Some(vec![
+                Point::new(1, 0),
+                Point::new(1, 1),
+                Point::new(0, -2),
+                Point::new(1, -2),
+            ]),
+            (Orientation::West, Orientation::South) => Some(vec![
+                Point::new(-1, 0),
+                Point::new(-1, -1),
+                Point::new(0, 2),
+                Point::new(-1, 2),
+            ]),
+            (Orientation::West, Orientation::North) => Some(vec![
+                Point::new(-1, 0),
+                Point::new(-1, -1),
+                Point::new(0, 2),
+                Point::new(-1, 2),
+            ]),
+            (Orientation::North, Orientation::West) => Some(vec![
+                Point::new(1, 0),
+                Point::new(1, 1),
+                Point::new(0, -2),
+                Point::new(1, -2),
+            ]),
+            _ => None,
+        },
+    }
+}
+
+/// The SRS-X 180 kicks, which try horizontal kicks before vertical ones.
+fn srs_x_half_kick_table(piece_kind: &PieceKind, from: &Orientation) -> Option<Vec<Point>> {
+    match piece_kind {
+        PieceKind::O => None,
+        PieceKind::I => match from {
+            Orientation::North => Some(vec![
+                Point::new(-1, 0),
+                Point::new(-2, 0),
+                Point::new(1, 0),
+                Point::new(2, 0),
+                Point::new(0, 1),
+            ]),
+            Orientation::South => Some(vec![
+                Point::new(1, 0),
+                Point::new(2, 0),
+                Point::new(-1, 0),
+                Point::new(-2, 0),
+                Point::new(0, -1),
+            ]),
+            Orientation::East => Some(vec![
+                Point::new(0, 1),
+                Point::new(0, 2),
+                Point::new(0, -1),
+                Point::new(0, -2),
+                Point::new(-1, 0),
+            ]),
+            Orientation::West => Some(vec![
+                Point::new(0, 1),
+                Point::new(0, 2),
+                Point::new(0, -1),
+                Point::new(0, -2),
+                Point::new(1, 0),
+            ]),
+        },
+        _ => match from {
+            Orientation::North => Some(vec![
+                Point::new(1, 0),
+                Point::new(2, 0),
+                Point::new(1, 1),
+                Point::new(2, 1),
+                Point::new(-1, 0),
+                Point::new(-2, 0),
+                Point::new(-1, 1),
+                Point::new(-2, 1),
+                Point::new(0, -1),
+                Point::new(3, 0),
+                Point::new(-3, 0),
+            ]),
+            Orientation::South => Some(vec![
+                Point::new(-1, 0),
+                Point::new(-2, 0),
+                Point::new(-1, -1),
+                Point::new(-2, -1),
+                Point::new(1, 0),
+                Point::new(2, 0),
+                Point::new(1, -1),
+                Point::new(2, -1),
+                Point::new(0, 1),
+                Point::new(-3, 0),
+                Point::new(3, 0),
+            ]),
+            Orientation::East => Some(vec![
+                Point::new(0, 1),
+                Point::new(0, 2),
+                Point::new(-1, 1),
+                Point::new(-1, 2),
+                Point::new(0, -1),
+                Point::new(0, -2),
+                Point::new(-1, -1),
+                Point::new(-1, -2),
+                Point::new(1, 0),
+                Point::new(0, 3),
+                Point::new(0, -3),
+            ]),
+            Orientation::West => Some(vec![
+                Point::new(0, 1),
+                Point::new(0, 2),
+                Point::new(1, 1),
+                Point::new(1, 2),
+                Point::new(0, -1),
+                Point::new(0, -2),
+                Point::new(1, -1),
+                Point::new(1, -2),
+                Point::new(-1, 0),
+                Point::new(0, 3),
+                Point::new(0, -3),
+            ]),
+        },
+    }
+}
+
+/// TETR.IO's I kicks, which mirror each other between clockwise and anticlockwise rotations.
+fn srs_plus_i_kick_table(from: &Orientation, to: &Orientation) -> Option<Vec<Point>> {
+    match (from, to) {
+        (Orientation::North, Orientation::East) => Some(vec![
+            Point::new(1, 0),
+            Point::new(-2, 0),
+            Point::new(-2, -1),
+            Point::new(1, 2),
+        ]),
+        (Orientation::East, Orientation::North) => Some(vec![
+            Point::new(-1, 0),
+            Point::new(2, 0),
+            Point::new(-1, -2),
+            Point::new(2, 1),
+        ]),
+        (Orientation::East, Orientation::South) => Some(vec![
+            Point::new(-1, 0),
+            Point::new(2, 0),
+            Point::new(-1, 2),
+            Point::new(2, -1),
+        ]),
+        (Orientation::South, Orientation::East) => Some(vec![
+            Point::new(-2, 0),
+            Point::new(1, 0),
+            Point::new(-2, 1),
+            Point::new(1, -2),
+        ]),
+        (Orientation::South, Orientation::West) => Some(vec![
+            Point::new(2, 0),
+            Point::new(-1, 0),
+            Point::new(2, 1),
+            Point::new(-1, -2),
+        ]),
+        (Orientation::West, Orientation::South) => Some(vec![
+            Point::new(1, 0),
+            Point::new(-2, 0),
+            Point::new(1, -2),
+            Point::new(-2, 1),
+        ]),
+        (Orientation::West, Orientation::North) => Some(vec![
+            Point::new(1, 0),
+            Point::new(-2, 0),
+            Point::new(1, 2),
+            Point::new(-2, -1),
+        ]),
+        (Orientation::North, Orientation::West) => Some(vec![
+            Point::new(-1, 0),
+            Point::new(2, 0),
+            Point::new(2, -1),
+            Point::new(-1, 2),
+        ]),
+        _ => None,
+    }
+}
@@ -1,7 +1,9 @@
 use crate::board::Board;
-use crate::config::Config;
+use crate::config::{Config, Gravity};
 use crate::piece::{Piece, PieceKind};
+use crate::scoring::Score;
 use crate::utils::direction::Direction;
+use crate::utils::point::Point;
 use crate::utils::rotation::Rotation;
 use std::convert::TryInto;
 use wasm_bindgen::prelude::*;
@@ -20,6 +22,14 @@ pub struct Game {
     /// Fixed queue size to reduce heap allocations.
     #[wasm_bindgen(skip)]
     pub queue: [Option<PieceKind>; 7],
+
+    /// The last move which changed the active piece, used to detect spins when it is placed.
+    #[wasm_bindgen(skip)]
+    pub last_move: Option<LastMove>,
+
+    /// The score so far, or `None` if the game is not scored.
+    #[wasm_bindgen(skip)]
+    pub score: Option<Score>,
 }
 
 impl Game {
@@ -30,29 +40,122 @@ impl Game {
             hold_kind: None,
             is_hold_used: false,
             queue: [None; 7],
+            last_move: None,
+            score: None,
         }
     }
 
     pub fn reduce(&self, config: &Config, action: &Action) -> Result<Game, ReduceError> {
+        self.reduce_with_outcome(config, action)
+            .map(|(game, _)| game)
+    }
+
+    /// Reduce the game, along with the outcome of the placement if the action placed a piece.
+    pub fn reduce_with_outcome(
+        &self,
+        config: &Config,
+        action: &Action,
+    ) -> Result<(Game, Option<PlaceOutcome>), ReduceError> {
         match action {
             Action::Move(mov) => self
                 .with_moved_piece(config, mov)
-                .map_err(|e| ReduceError::Move(e)),
+                .map(|game| (game, None))
+                .map_err(ReduceError::Move),
             Action::Hold { switch } => self
                 .with_hold_used(config, *switch)
-                .map_err(|e| ReduceError::Hold(e)),
+                .map(|game| (game, None))
+                .map_err(ReduceError::Hold),
             Action::Place => self
                 .with_placed_piece(config)
-                .map_err(|e| ReduceError::Place(e)),
+                .map(|(game, outcome)| (game, Some(outcome)))
+                .map_err(ReduceError::Place),
+            Action::ReceiveGarbage {
+                line_count,
+                hole_column,
+            } => self
+                .with_received_garbage(config, *line_count, *hole_column)
+                .map(|game| (game, None))
+                .map_err(ReduceError::Garbage),
+        }
+    }
+
+    /// Spawn `kind` as the active piece, applying the initial actions held while it was waiting
+    /// to spawn. An initial rotation does not kick, and the piece spawns unrotated instead if the
+    /// rotated piece does not fit. A rotated piece can spawn where the unrotated piece would top
+    /// out.
+    pub fn with_spawned_piece(
+        &self,
+        config: &Config,
+        kind: &PieceKind,
+        initial: &InitialActions,
+    ) -> Result<Game, SpawnError> {
+        if let Some(rotation) = initial.rotation {
+            let is_rotation_allowed = config.initial_rotation
+                && (rotation != Rotation::Half || config.is_half_rotation_allowed());
+            if !is_rotation_allowed {
+                return Err(SpawnError::InitialRotationNotAllowed);
+            }
+        }
+        if initial.hold && !config.initial_hold {
+            return Err(SpawnError::InitialHoldNotAllowed);
+        }
+
+        let (spawn_kind, hold_kind) = if initial.hold {
+            let Some(hold_kind) = self.hold_kind else {
+                return Err(SpawnError::NoHoldPiece);
+            };
+            (hold_kind, Some(*kind))
+        } else {
+            (*kind, self.hold_kind)
+        };
+
+        let spawned_piece = Piece::spawn(config, &spawn_kind);
+        let rotated_piece = initial.rotation.and_then(|rotation| {
+            Piece {
+                orientation: spawned_piece.orientation.rotated(&rotation),
+                ..spawned_piece
+            }
+            .fitted_on_board(config, &self.board)
+        });
+        let Some(next_piece) =
+            rotated_piece.or_else(|| spawned_piece.fitted_on_board(config, &self.board))
+        else {
+            return Err(SpawnError::PieceCollision);
+        };
+
+        Ok(Game {
+            piece: Some(next_piece),
+            hold_kind,
+            // A piece spawning after holding into an empty hold cannot be held again.
+            is_hold_used: self.is_hold_used || initial.hold,
+            last_move: None,
+            ..self.clone()
         }
+        .with_gravity(config))
     }
 
     fn with_moved_piece(&self, config: &Config, mov: &Move) -> Result<Game, MoveError> {
         match mov {
-            Move::Rotate(rotation) => self.with_rotated_piece(config, &rotation),
-            Move::Translate(direction) => self.with_translated_piece(config, &direction),
+            Move::Rotate(rotation) => self.with_rotated_piece(config, rotation),
+            Move::Translate(direction) => self.with_translated_piece(config, direction),
             Move::Drop => self.with_dropped_piece(config),
         }
+        .map(|game| game.with_gravity(config))
+    }
+
+    /// The game after gravity pulls the active piece down. Gravity is not a move, so the last move
+    /// is kept and a piece can still spin into place as it sinks.
+    fn with_gravity(self, config: &Config) -> Game {
+        match config.gravity {
+            Gravity::Zero => self,
+            Gravity::TwentyG => match self.with_dropped_piece(config) {
+                Ok(game) => Game {
+                    last_move: self.last_move,
+                    ..game
+                },
+                Err(_) => self,
+            },
+        }
     }
 
     fn with_rotated_piece(&self, config: &Config, rotation: &Rotation) -> Result<Game, MoveError> {
@@ -60,18 +163,26 @@ impl Game {
             return Err(MoveError::NoPiece);
         };
 
+        if *rotation == Rotation::Half && !config.is_half_rotation_allowed() {
+            return Err(MoveError::InvalidMove);
+        }
+
         let from_orientation = piece.orientation;
         let to_orientation = from_orientation.rotated(rotation);
 
         let mut rotated_piece = Piece {
             orientation: to_orientation,
-            ..piece.clone()
+            ..*piece
         };
-        let piece_points = rotated_piece.get_points(config);
+        let piece_mask = rotated_piece.get_mask(config);
 
-        if self.board.can_fit(&piece_points) {
+        if self.board.can_fit_mask(&piece_mask) {
             return Ok(Game {
                 piece: Some(rotated_piece),
+                last_move: Some(LastMove {
+                    mov: Move::Rotate(*rotation),
+                    kick_index: None,
+                }),
                 ..self.clone()
             });
         }
@@ -80,12 +191,15 @@ impl Game {
             return Err(MoveError::InvalidMove);
         };
 
-        for kick in kicks {
-            let kicked_points = piece_points.map(|point| point + kick);
-            if self.board.can_fit(&kicked_points) {
+        for (kick_index, kick) in kicks.into_iter().enumerate() {
+            if self.board.can_fit_mask(&piece_mask.moved(kick)) {
                 rotated_piece.position += kick;
                 return Ok(Game {
                     piece: Some(rotated_piece),
+                    last_move: Some(LastMove {
+                        mov: Move::Rotate(*rotation),
+                        kick_index: Some(kick_index),
+                    }),
                     ..self.clone()
                 });
             }
@@ -107,17 +221,19 @@ impl Game {
 
         let next_piece = Piece {
             position: piece.position + direction_offset,
-            ..piece.clone()
+            ..*piece
         };
 
-        let next_piece_points = next_piece.get_points(config);
-
-        if !self.board.can_fit(&next_piece_points) {
+        if !self.board.can_fit_mask(&next_piece.get_mask(config)) {
             return Err(MoveError::InvalidMove);
         }
 
         Ok(Game {
             piece: Some(next_piece),
+            last_move: Some(LastMove {
+                mov: Move::Translate(*direction),
+                kick_index: None,
+            }),
             ..self.clone()
         })
     }
@@ -127,12 +243,13 @@ impl Game {
             return Err(MoveError::NoPiece);
         };
 
-        let mut dropped_piece = piece.clone();
+        let mut dropped_piece = *piece;
+        let mut dropped_mask = piece.get_mask(config);
 
-        while self.board.can_fit(&dropped_piece.get_points(config)) {
-            dropped_piece.position.y -= 1;
+        while self.board.can_fit_mask(&dropped_mask) {
+            dropped_mask.position.y -= 1;
         }
-        dropped_piece.position.y += 1;
+        dropped_piece.position.y = dropped_mask.position.y + 1;
 
         if dropped_piece.position.y == piece.position.y {
             return Err(MoveError::InvalidMove);
@@ -140,6 +257,10 @@ impl Game {
 
         Ok(Game {
             piece: Some(dropped_piece),
+            last_move: Some(LastMove {
+                mov: Move::Drop,
+                kick_index: None,
+            }),
             ..self.clone()
         })
     }
@@ -157,14 +278,22 @@ impl Game {
         }
 
         let Some(hold_kind) = self.hold_kind.as_ref() else {
-            return Err(HoldError::NoHoldPiece);
+            let Some(piece) = self.piece.as_ref() else {
+                return Err(HoldError::NoPiece);
+            };
+            // The next piece spawns from the queue in place of the empty hold.
+            return Ok(Game {
+                is_hold_used: true,
+                piece: None,
+                hold_kind: Some(piece.kind),
+                last_move: None,
+                ..self.clone()
+            });
         };
 
-        let next_piece = Piece::spawn(config, &hold_kind);
-
-        if !self.board.can_fit(&next_piece.get_points(config)) {
+        let Some(next_piece) = Piece::spawn_on_board(config, hold_kind, &self.board) else {
             return Err(HoldError::PieceCollision);
-        }
+        };
 
         let Some(piece) = self.piece.as_ref() else {
             return Err(HoldError::NoPiece);
@@ -174,36 +303,160 @@ impl Game {
             is_hold_used: true,
             piece: Some(next_piece),
             hold_kind: Some(piece.kind),
+            last_move: None,
             ..self.clone()
-        })
+        }
+        .with_gravity(config))
     }
 
-    fn with_placed_piece(&self, config: &Config) -> Result<Game, PlaceError> {
+    /// The game after the active piece is placed, along with the outcome of the placement.
+    pub fn with_placed_piece(&self, config: &Config) -> Result<(Game, PlaceOutcome), PlaceError> {
         let Some(piece) = &self.piece else {
             return Err(PlaceError::NoPiece);
         };
 
-        let piece_points = piece.get_points(config);
+        let piece_mask = piece.get_mask(config);
 
-        if !self.board.can_place(&piece_points) {
+        if !self.board.can_place_mask(&piece_mask) {
             return Err(PlaceError::PieceInAir);
         }
 
+        let spin = self.get_spin(config);
+
         let next_game = self.clone();
         let mut next_board = next_game.board;
 
-        next_board.fill_piece_points(&piece_points);
-        if !next_board.can_perfect_clear() {
+        next_board.fill_mask(&piece_mask);
+        let cleared_rows = (0..Board::HEIGHT)
+            .filter(|&y| next_board.is_line_filled(y))
+            .collect::<Vec<_>>();
+        let is_perfect_clear = next_board.can_perfect_clear();
+        if !is_perfect_clear {
             next_board.clear_filled_lines();
         }
 
+        let outcome = PlaceOutcome {
+            cleared_rows,
+            is_perfect_clear,
+            spin,
+        };
+
+        Ok((
+            Game {
+                board: next_board,
+                piece: None,
+                is_hold_used: false,
+                last_move: None,
+                score: next_game.score.map(|score| score.with_outcome(&outcome)),
+                ..next_game
+            },
+            outcome,
+        ))
+    }
+
+    /// The game after a batch of garbage lines sharing one hole column is pushed under the stack.
+    fn with_received_garbage(
+        &self,
+        config: &Config,
+        line_count: u8,
+        hole_column: isize,
+    ) -> Result<Game, GarbageError> {
+        if !(0..Board::WIDTH).contains(&hole_column) {
+            return Err(GarbageError::InvalidHoleColumn);
+        }
+        if self.board.stack_height() + line_count as isize > Board::HEIGHT {
+            return Err(GarbageError::ToppedOut);
+        }
+
+        let mut next_board = self.board;
+        next_board.push_garbage(line_count, hole_column);
+
+        if let Some(piece) = &self.piece {
+            if !next_board.can_fit_mask(&piece.get_mask(config)) {
+                return Err(GarbageError::PieceCollision);
+            }
+        }
+
         Ok(Game {
             board: next_board,
-            piece: None,
-            is_hold_used: false,
-            ..next_game
+            ..self.clone()
         })
     }
+
+    /**
+    The spin the active piece would be placed with, if its last move was a rotation.
+
+    A T piece spins if three of the four corners around its center are filled, and it is a full
+    T-spin if both corners it points towards are filled. A rotation using the fourth kick of a
+    quarter turn, which SRS uses for T-spin triples, also counts as a full T-spin. Any other piece
+    spins if it cannot move left, right, or up, except for the O piece, which looks the same in
+    every orientation and never spins.
+    */
+    pub fn get_spin(&self, config: &Config) -> Option<Spin> {
+        let piece = self.piece.as_ref()?;
+        let LastMove {
+            mov: Move::Rotate(rotation),
+            kick_index,
+        } = self.last_move?
+        else {
+            return None;
+        };
+
+        match piece.kind {
+            PieceKind::T => return self.get_t_spin(config, piece, rotation, kick_index),
+            PieceKind::O => return None,
+            _ => {}
+        }
+
+        let piece_mask = piece.get_mask(config);
+        let is_immobile = [Point::new(-1, 0), Point::new(1, 0), Point::new(0, 1)]
+            .iter()
+            .all(|offset| !self.board.can_fit_mask(&piece_mask.moved(*offset)));
+        is_immobile.then_some(Spin::AllSpin)
+    }
+
+    fn get_t_spin(
+        &self,
+        config: &Config,
+        piece: &Piece,
+        rotation: Rotation,
+        kick_index: Option<usize>,
+    ) -> Option<Spin> {
+        let points = piece.get_points(config);
+        let is_adjacent = |a: &Point, b: &Point| (a.x - b.x).abs() + (a.y - b.y).abs() == 1;
+        let center = *points.iter().find(|point| {
+            points
+                .iter()
+                .filter(|other| is_adjacent(point, other))
+                .count()
+                == 3
+        })?;
+        // The only cell without another cell opposite it across the center.
+        let nose = *points
+            .iter()
+            .find(|&&point| point != center && !points.contains(&(center - (point - center))))?;
+
+        let facing = nose - center;
+        let side = Point::new(facing.y, facing.x);
+        let count_filled = |corners: [Point; 2]| {
+            corners
+                .iter()
+                .filter(|corner| self.board.is_filled(corner))
+                .count()
+        };
+        let front_count = count_filled([nose + side, nose - side]);
+        let back_count = count_filled([center - facing + side, center - facing - side]);
+
+        if front_count + back_count < 3 {
+            return None;
+        }
+        let is_triple_kick = rotation != Rotation::Half && kick_index == Some(3);
+        if front_count == 2 || is_triple_kick {
+            Some(Spin::TSpin)
+        } else {
+            Some(Spin::TSpinMini)
+        }
+    }
 }
 
 #[wasm_bindgen]
@@ -220,6 +473,8 @@ impl Game {
             piece,
             hold_kind,
             is_hold_used,
+            last_move: None,
+            score: None,
             queue: {
                 let mut queue = [u8::MAX; 7];
                 js_queue.copy_to(&mut queue[..js_queue.length() as usize]);
@@ -243,8 +498,31 @@ impl Game {
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Action {
     Move(Move),
-    Hold { switch: bool },
+    Hold {
+        switch: bool,
+    },
     Place,
+    /// Receive `line_count` garbage lines which all have their hole in `hole_column`.
+    ReceiveGarbage {
+        line_count: u8,
+        hole_column: isize,
+    },
+}
+
+/// Inputs held while a piece is waiting to spawn, which take effect as it spawns.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InitialActions {
+    pub rotation: Option<Rotation>,
+
+    /// Whether the piece is switched with the hold piece as it spawns.
+    pub hold: bool,
+}
+
+impl InitialActions {
+    pub const NONE: InitialActions = InitialActions {
+        rotation: None,
+        hold: false,
+    };
 }
 
 #[derive(Debug, PartialEq)]
@@ -252,6 +530,7 @@ pub enum ReduceError {
     Move(MoveError),
     Hold(HoldError),
     Place(PlaceError),
+    Garbage(GarbageError),
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -261,6 +540,45 @@ pub enum Move {
     Drop,
 }
 
+/// The last successful move of the active piece.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LastMove {
+    pub mov: Move,
+
+    /// The index of the kick used by a rotation, or `None` if the piece rotated without a kick.
+    pub kick_index: Option<usize>,
+}
+
+/**
+The result of placing a piece.
+
+A perfect clear leaves its filled lines on the board so that the solver can recognise it, but they
+are still reported as cleared.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlaceOutcome {
+    /// The rows which were filled by the placement, from the bottom up.
+    pub cleared_rows: Vec<isize>,
+
+    pub is_perfect_clear: bool,
+
+    pub spin: Option<Spin>,
+}
+
+impl PlaceOutcome {
+    pub fn lines_cleared(&self) -> usize {
+        self.cleared_rows.len()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Spin {
+    TSpin,
+    TSpinMini,
+    /// A piece other than T which cannot move left, right, or up after rotating into place.
+    AllSpin,
+}
+
 #[derive(Debug, PartialEq)]
 pub enum MoveError {
     NoPiece,
@@ -270,20 +588,36 @@ pub enum MoveError {
 #[derive(Debug, PartialEq)]
 pub enum HoldError {
     NotAvailable,
-    NoHoldPiece,
     NoPiece,
     PieceCollision,
 }
 
+#[derive(Debug, PartialEq)]
+pub enum SpawnError {
+    InitialRotationNotAllowed,
+    InitialHoldNotAllowed,
+    NoHoldPiece,
+    PieceCollision,
+}
+
 #[derive(Debug, PartialEq)]
 pub enum PlaceError {
     NoPiece,
     PieceInAir,
 }
 
+#[derive(Debug, PartialEq)]
+pub enum GarbageError {
+    InvalidHoleColumn,
+    /// The stack would be pushed above the top of the board.
+    ToppedOut,
+    /// The active piece would overlap the stack after it is pushed up.
+    PieceCollision,
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::utils::point::Point;
+    use crate::test_utils::queue_of;
 
     use super::*;
 
@@ -530,142 +864,926 @@ mod tests {
                 }
             }
         }
-    }
 
-    mod with_translation {
-        use super::*;
+        mod half_rotation {
+            use crate::config::HalfKick;
 
-        #[test]
-        fn moves_piece() {
-            let game = Game {
-                piece: Some(Piece {
-                    position: Point::new(3, -1),
-                    ..Piece::spawn(&CONFIG, &PieceKind::I)
-                }),
-                ..Game::initial()
-            };
+            use super::*;
 
-            let next_game = game.with_moved_piece(&CONFIG, &Move::Translate(Direction::Down));
+            fn game_with_grounded_t_piece() -> Game {
+                Game {
+                    piece: Some(Piece {
+                        kind: PieceKind::T,
+                        orientation: Orientation::North,
+                        position: Point::new(3, -1),
+                    }),
+                    ..Game::initial()
+                }
+            }
 
-            assert!(next_game.is_ok());
-            let next_game = next_game.unwrap();
+            #[test]
+            fn not_allowed_by_default() {
+                let game = Game {
+                    piece: Some(Piece::spawn(&CONFIG, &PieceKind::T)),
+                    ..Game::initial()
+                };
 
-            let piece = next_game.piece.as_ref().unwrap();
-            assert_eq!(piece.position, Point::new(3, -2));
+                let next_game = game.with_moved_piece(&CONFIG, &Move::Rotate(Rotation::Half));
 
-            let next_game = next_game.with_moved_piece(&CONFIG, &Move::Translate(Direction::Left));
+                assert_eq!(next_game, Err(MoveError::InvalidMove));
+            }
 
-            assert!(next_game.is_ok());
-            let next_game = next_game.unwrap();
+            #[test]
+            fn no_kick() {
+                let config = Config {
+                    half_rotation: Some(HalfKick::NoKicks),
+                    ..Config::default()
+                };
+                let game = Game {
+                    piece: Some(Piece::spawn(&config, &PieceKind::T)),
+                    ..Game::initial()
+                };
 
-            let piece = next_game.piece.as_ref().unwrap();
-            assert_eq!(piece.position, Point::new(2, -2));
+                let next_game = game
+                    .with_moved_piece(&config, &Move::Rotate(Rotation::Half))
+                    .unwrap();
 
-            let next_game = next_game.with_moved_piece(&CONFIG, &Move::Translate(Direction::Right));
+                let next_piece = next_game.piece.unwrap();
+                assert_eq!(next_piece.orientation, Orientation::South);
+                assert_eq!(next_piece.position, game.piece.unwrap().position);
+            }
 
-            assert!(next_game.is_ok());
-            let next_game = next_game.unwrap();
+            #[test]
+            fn blocked_without_kicks() {
+                let config = Config {
+                    half_rotation: Some(HalfKick::NoKicks),
+                    ..Config::default()
+                };
+                let game = game_with_grounded_t_piece();
 
-            let piece = next_game.piece.as_ref().unwrap();
-            assert_eq!(piece.position, Point::new(3, -2));
+                let next_game = game.with_moved_piece(&config, &Move::Rotate(Rotation::Half));
 
-            let next_game = next_game.with_moved_piece(&CONFIG, &Move::Translate(Direction::Down));
-            assert_eq!(next_game, Err(MoveError::InvalidMove));
-        }
-    }
+                assert_eq!(next_game, Err(MoveError::InvalidMove));
+            }
 
-    mod with_drop {
-        use super::*;
+            #[test]
+            fn tetr_io_kick() {
+                let config = Config {
+                    half_rotation: Some(HalfKick::TetrIO),
+                    ..Config::default()
+                };
+                let game = game_with_grounded_t_piece();
 
-        #[test]
-        fn i_piece_on_floor_in_empty_board() {
-            let game = Game {
-                piece: Some(Piece::spawn(&CONFIG, &PieceKind::I)),
-                ..Game::initial()
-            };
+                let next_game = game
+                    .with_moved_piece(&config, &Move::Rotate(Rotation::Half))
+                    .unwrap();
 
-            let next_game = game.reduce(&CONFIG, &Action::Move(Move::Drop));
+                let next_piece = next_game.piece.unwrap();
+                assert_eq!(next_piece.orientation, Orientation::South);
+                assert_eq!(next_piece.position, Point::new(3, 0));
+            }
 
-            assert!(next_game.is_ok());
-            let next_game = next_game.unwrap();
+            #[test]
+            fn ppt_kick() {
+                let config = Config {
+                    half_rotation: Some(HalfKick::PPT),
+                    ..Config::default()
+                };
+                let game = game_with_grounded_t_piece();
 
-            assert_eq!(next_game.piece.unwrap().position.y, -2);
+                let next_game = game
+                    .with_moved_piece(&config, &Move::Rotate(Rotation::Half))
+                    .unwrap();
+
+                let next_piece = next_game.piece.unwrap();
+                assert_eq!(next_piece.orientation, Orientation::South);
+                assert_eq!(next_piece.position, Point::new(4, 0));
+            }
         }
 
-        #[test]
-        fn o_piece_on_floor_in_empty_board() {
-            let game = Game {
-                piece: Some(Piece::spawn(&CONFIG, &PieceKind::O)),
-                ..Game::initial()
-            };
+        mod srs_plus {
+            use crate::config::Kick;
 
-            let next_game = game.reduce(&CONFIG, &Action::Move(Move::Drop));
+            use super::*;
 
-            assert!(next_game.is_ok());
-            let next_game = next_game.unwrap();
+            fn srs_plus_config() -> Config {
+                Config {
+                    kick: Kick::SRSPlus,
+                    ..Config::default()
+                }
+            }
 
-            assert_eq!(next_game.piece.unwrap().position.y, -1);
-        }
+            fn game_with_blocked_i_piece() -> Game {
+                let mut board = Board::empty_board();
+                board.fill(&Point::new(5, 0));
+                Game {
+                    board,
+                    piece: Some(Piece {
+                        kind: PieceKind::I,
+                        orientation: Orientation::North,
+                        position: Point::new(3, 0),
+                    }),
+                    ..Game::initial()
+                }
+            }
 
-        #[test]
-        fn i_piece_on_filled_cell() {
-            let game = Game {
-                board: {
-                    let mut b = Board::empty_board();
-                    b.fill(&Point::new(3, 10));
-                    b
-                },
-                piece: Some(Piece::spawn(&CONFIG, &PieceKind::I)),
-                ..Game::initial()
-            };
+            #[test]
+            fn i_piece_kick_one() {
+                let game = game_with_blocked_i_piece();
 
-            let next_game = game.reduce(&CONFIG, &Action::Move(Move::Drop));
+                let next_game = game
+                    .with_moved_piece(&srs_plus_config(), &Move::Rotate(Rotation::Clockwise))
+                    .unwrap();
 
-            assert!(next_game.is_ok());
-            let next_game = next_game.unwrap();
+                let next_piece = next_game.piece.unwrap();
+                assert_eq!(next_piece.orientation, Orientation::East);
+                assert_eq!(next_piece.position, Point::new(4, 0));
+            }
 
-            assert_eq!(next_game.piece.unwrap().position.y, 9);
-        }
-    }
+            #[test]
+            fn i_piece_differs_from_srs() {
+                let game = game_with_blocked_i_piece();
 
-    mod with_hold_used {
-        use super::*;
+                let next_game = game
+                    .with_moved_piece(&CONFIG, &Move::Rotate(Rotation::Clockwise))
+                    .unwrap();
 
-        #[test]
-        fn invalid_if_no_active_piece() {
-            let game = Game {
-                hold_kind: Some(PieceKind::J),
-                ..Game::initial()
-            };
+                assert_eq!(next_game.piece.unwrap().position, Point::new(1, 0));
+            }
 
-            let next_game = game.reduce(&CONFIG, &Action::Hold { switch: true });
+            #[test]
+            fn t_piece_same_as_srs() {
+                let game = Game {
+                    piece: Some(Piece {
+                        kind: PieceKind::T,
+                        orientation: Orientation::East,
+                        position: Point::new(-1, 0),
+                    }),
+                    ..Game::initial()
+                };
 
-            assert_eq!(next_game, Err(ReduceError::Hold(HoldError::NoPiece)));
+                let srs_game = game
+                    .with_moved_piece(&CONFIG, &Move::Rotate(Rotation::AntiClockwise))
+                    .unwrap();
+                let srs_plus_game = game
+                    .with_moved_piece(&srs_plus_config(), &Move::Rotate(Rotation::AntiClockwise))
+                    .unwrap();
+
+                assert_eq!(srs_game.piece.unwrap().position, Point::new(0, 0));
+                assert_eq!(srs_game, srs_plus_game);
+            }
         }
 
-        #[test]
-        fn invalid_if_no_hold_piece() {
-            let game = Game {
-                piece: Some(Piece::spawn(&CONFIG, &PieceKind::I)),
-                ..Game::initial()
-            };
+        mod srs_x {
+            use crate::config::{HalfKick, Kick};
 
-            let next_game = game.reduce(&CONFIG, &Action::Hold { switch: true });
+            use super::*;
 
-            assert_eq!(next_game, Err(ReduceError::Hold(HoldError::NoHoldPiece)));
-        }
+            fn srs_x_config() -> Config {
+                Config {
+                    kick: Kick::SRSX,
+                    ..Config::default()
+                }
+            }
 
-        #[test]
-        fn invalid_if_new_piece_intersects_board() {
-            let mut board = Board::empty_board();
-            for x in 3..7 {
-                board.fill(&Point::new(x, 20));
+            fn game_with_grounded_t_piece() -> Game {
+                Game {
+                    piece: Some(Piece {
+                        kind: PieceKind::T,
+                        orientation: Orientation::North,
+                        position: Point::new(3, -1),
+                    }),
+                    ..Game::initial()
+                }
             }
 
-            let game = Game {
-                board,
-                hold_kind: Some(PieceKind::I),
-                piece: Some(Piece::spawn(&CONFIG, &PieceKind::J)),
+            #[test]
+            fn half_rotation_allowed_without_half_rotation() {
+                let game = Game {
+                    piece: Some(Piece::spawn(&CONFIG, &PieceKind::T)),
+                    ..Game::initial()
+                };
+
+                let srs_game = game.with_moved_piece(&CONFIG, &Move::Rotate(Rotation::Half));
+                let srs_x_game = game
+                    .with_moved_piece(&srs_x_config(), &Move::Rotate(Rotation::Half))
+                    .unwrap();
+
+                assert_eq!(srs_game, Err(MoveError::InvalidMove));
+                assert_eq!(srs_x_game.piece.unwrap().orientation, Orientation::South);
+            }
+
+            #[test]
+            fn kick_one() {
+                let game = game_with_grounded_t_piece();
+
+                let next_game = game
+                    .with_moved_piece(&srs_x_config(), &Move::Rotate(Rotation::Half))
+                    .unwrap();
+
+                let next_piece = next_game.piece.unwrap();
+                assert_eq!(next_piece.orientation, Orientation::South);
+                assert_eq!(next_piece.position, Point::new(4, 0));
+                assert_eq!(next_game.last_move.unwrap().kick_index, Some(2));
+            }
+
+            #[test]
+            fn ignores_half_rotation() {
+                for half_rotation in [HalfKick::NoKicks, HalfKick::TetrIO] {
+                    let config = Config {
+                        half_rotation: Some(half_rotation),
+                        ..srs_x_config()
+                    };
+                    let game = game_with_grounded_t_piece();
+
+                    let next_game = game
+                        .with_moved_piece(&config, &Move::Rotate(Rotation::Half))
+                        .unwrap();
+
+                    assert_eq!(next_game.piece.unwrap().position, Point::new(4, 0));
+                }
+            }
+        }
+
+        mod ars {
+            use crate::config::Kick;
+
+            use super::*;
+
+            fn ars_config() -> Config {
+                Config {
+                    kick: Kick::ARS,
+                    ..Config::default()
+                }
+            }
+
+            #[test]
+            fn no_kick() {
+                let game = Game {
+                    piece: Some(Piece::spawn(&ars_config(), &PieceKind::T)),
+                    ..Game::initial()
+                };
+
+                let next_game = game
+                    .with_moved_piece(&ars_config(), &Move::Rotate(Rotation::Clockwise))
+                    .unwrap();
+
+                let next_piece = next_game.piece.unwrap();
+                assert_eq!(next_piece.orientation, Orientation::East);
+                assert_eq!(next_piece.position, game.piece.unwrap().position);
+            }
+
+            #[test]
+            fn kick_right() {
+                let game = Game {
+                    piece: Some(Piece {
+                        kind: PieceKind::T,
+                        orientation: Orientation::West,
+                        position: Point::new(-1, 0),
+                    }),
+                    ..Game::initial()
+                };
+
+                let next_game = game
+                    .with_moved_piece(&ars_config(), &Move::Rotate(Rotation::Clockwise))
+                    .unwrap();
+
+                let next_piece = next_game.piece.unwrap();
+                assert_eq!(next_piece.orientation, Orientation::North);
+                assert_eq!(next_piece.position, Point::new(0, 0));
+            }
+
+            #[test]
+            fn kick_left() {
+                let game = Game {
+                    piece: Some(Piece {
+                        kind: PieceKind::T,
+                        orientation: Orientation::East,
+                        position: Point::new(8, 0),
+                    }),
+                    ..Game::initial()
+                };
+
+                let next_game = game
+                    .with_moved_piece(&ars_config(), &Move::Rotate(Rotation::Clockwise))
+                    .unwrap();
+
+                let next_piece = next_game.piece.unwrap();
+                assert_eq!(next_piece.orientation, Orientation::South);
+                assert_eq!(next_piece.position, Point::new(7, 0));
+            }
+
+            #[test]
+            fn i_piece_never_kicks() {
+                let game = Game {
+                    piece: Some(Piece {
+                        kind: PieceKind::I,
+                        orientation: Orientation::East,
+                        position: Point::new(7, 0),
+                    }),
+                    ..Game::initial()
+                };
+
+                let next_game =
+                    game.with_moved_piece(&ars_config(), &Move::Rotate(Rotation::AntiClockwise));
+
+                assert_eq!(next_game, Err(MoveError::InvalidMove));
+            }
+        }
+
+        mod custom {
+            use crate::config::{HalfKick, Kick};
+
+            use super::*;
+
+            #[test]
+            fn kicks_from_table() {
+                let table = "T N E: 0,-5 0,-4 0,-3 0,-2 0,-1 2,1".parse().unwrap();
+                let config = Config {
+                    kick: Kick::Custom(table),
+                    ..Config::default()
+                };
+                let game = Game {
+                    piece: Some(Piece {
+                        kind: PieceKind::T,
+                        orientation: Orientation::North,
+                        position: Point::new(3, -1),
+                    }),
+                    ..Game::initial()
+                };
+
+                let next_game = game
+                    .with_moved_piece(&config, &Move::Rotate(Rotation::Clockwise))
+                    .unwrap();
+
+                let next_piece = next_game.piece.unwrap();
+                assert_eq!(next_piece.orientation, Orientation::East);
+                assert_eq!(next_piece.position, Point::new(5, 0));
+            }
+
+            #[test]
+            fn missing_transition_does_not_kick() {
+                let table = "T N E: 2,1".parse().unwrap();
+                let config = Config {
+                    kick: Kick::Custom(table),
+                    ..Config::default()
+                };
+                let game = Game {
+                    piece: Some(Piece {
+                        kind: PieceKind::T,
+                        orientation: Orientation::North,
+                        position: Point::new(3, -1),
+                    }),
+                    ..Game::initial()
+                };
+
+                let next_game =
+                    game.with_moved_piece(&config, &Move::Rotate(Rotation::AntiClockwise));
+
+                assert_eq!(next_game, Err(MoveError::InvalidMove));
+            }
+
+            #[test]
+            fn missing_half_transition_does_not_kick() {
+                let table = "T N E: 2,1".parse().unwrap();
+                let half_config = Config {
+                    half_rotation: Some(HalfKick::TetrIO),
+                    ..Config::default()
+                };
+                let config = Config {
+                    kick: Kick::Custom(table),
+                    ..half_config.clone()
+                };
+                let game = Game {
+                    piece: Some(Piece {
+                        kind: PieceKind::T,
+                        orientation: Orientation::North,
+                        position: Point::new(3, -1),
+                    }),
+                    ..Game::initial()
+                };
+
+                let half_game = game.with_moved_piece(&half_config, &Move::Rotate(Rotation::Half));
+                let next_game = game.with_moved_piece(&config, &Move::Rotate(Rotation::Half));
+
+                assert!(half_game.is_ok());
+                assert_eq!(next_game, Err(MoveError::InvalidMove));
+            }
+        }
+
+        mod no_kicks {
+            use crate::config::Kick;
+
+            use super::*;
+
+            #[test]
+            fn blocked_rotation_fails() {
+                let config = Config {
+                    kick: Kick::NoKicks,
+                    ..Config::default()
+                };
+                let game = Game {
+                    piece: Some(Piece {
+                        kind: PieceKind::I,
+                        orientation: Orientation::East,
+                        position: Point::new(-2, 0),
+                    }),
+                    ..Game::initial()
+                };
+
+                let next_game =
+                    game.with_moved_piece(&config, &Move::Rotate(Rotation::AntiClockwise));
+
+                assert_eq!(next_game, Err(MoveError::InvalidMove));
+                assert!(game
+                    .with_moved_piece(&CONFIG, &Move::Rotate(Rotation::AntiClockwise))
+                    .is_ok());
+            }
+        }
+    }
+
+    mod with_translation {
+        use super::*;
+
+        #[test]
+        fn moves_piece() {
+            let game = Game {
+                piece: Some(Piece {
+                    position: Point::new(3, -1),
+                    ..Piece::spawn(&CONFIG, &PieceKind::I)
+                }),
+                ..Game::initial()
+            };
+
+            let next_game = game.with_moved_piece(&CONFIG, &Move::Translate(Direction::Down));
+
+            assert!(next_game.is_ok());
+            let next_game = next_game.unwrap();
+
+            let piece = next_game.piece.as_ref().unwrap();
+            assert_eq!(piece.position, Point::new(3, -2));
+
+            let next_game = next_game.with_moved_piece(&CONFIG, &Move::Translate(Direction::Left));
+
+            assert!(next_game.is_ok());
+            let next_game = next_game.unwrap();
+
+            let piece = next_game.piece.as_ref().unwrap();
+            assert_eq!(piece.position, Point::new(2, -2));
+
+            let next_game = next_game.with_moved_piece(&CONFIG, &Move::Translate(Direction::Right));
+
+            assert!(next_game.is_ok());
+            let next_game = next_game.unwrap();
+
+            let piece = next_game.piece.as_ref().unwrap();
+            assert_eq!(piece.position, Point::new(3, -2));
+
+            let next_game = next_game.with_moved_piece(&CONFIG, &Move::Translate(Direction::Down));
+            assert_eq!(next_game, Err(MoveError::InvalidMove));
+        }
+    }
+
+    mod with_drop {
+        use super::*;
+
+        #[test]
+        fn i_piece_on_floor_in_empty_board() {
+            let game = Game {
+                piece: Some(Piece::spawn(&CONFIG, &PieceKind::I)),
+                ..Game::initial()
+            };
+
+            let next_game = game.reduce(&CONFIG, &Action::Move(Move::Drop));
+
+            assert!(next_game.is_ok());
+            let next_game = next_game.unwrap();
+
+            assert_eq!(next_game.piece.unwrap().position.y, -2);
+        }
+
+        #[test]
+        fn o_piece_on_floor_in_empty_board() {
+            let game = Game {
+                piece: Some(Piece::spawn(&CONFIG, &PieceKind::O)),
+                ..Game::initial()
+            };
+
+            let next_game = game.reduce(&CONFIG, &Action::Move(Move::Drop));
+
+            assert!(next_game.is_ok());
+            let next_game = next_game.unwrap();
+
+            assert_eq!(next_game.piece.unwrap().position.y, -1);
+        }
+
+        #[test]
+        fn i_piece_on_filled_cell() {
+            let game = Game {
+                board: {
+                    let mut b = Board::empty_board();
+                    b.fill(&Point::new(3, 10));
+                    b
+                },
+                piece: Some(Piece::spawn(&CONFIG, &PieceKind::I)),
+                ..Game::initial()
+            };
+
+            let next_game = game.reduce(&CONFIG, &Action::Move(Move::Drop));
+
+            assert!(next_game.is_ok());
+            let next_game = next_game.unwrap();
+
+            assert_eq!(next_game.piece.unwrap().position.y, 9);
+        }
+    }
+
+    mod with_twenty_gravity {
+        use crate::config::Kick;
+        use crate::utils::rotation::Orientation;
+
+        use super::*;
+
+        fn twenty_gravity_config() -> Config {
+            Config {
+                gravity: Gravity::TwentyG,
+                ..Config::default()
+            }
+        }
+
+        #[test]
+        fn piece_sinks_on_spawn() {
+            let config = twenty_gravity_config();
+
+            let next_game = Game::initial()
+                .with_spawned_piece(&config, &PieceKind::T, &InitialActions::NONE)
+                .unwrap();
+
+            assert_eq!(next_game.piece.unwrap().position, Point::new(3, -1));
+            assert!(next_game.reduce(&config, &Action::Place).is_ok());
+        }
+
+        #[test]
+        fn piece_sinks_after_translation() {
+            let config = twenty_gravity_config();
+            let mut board = Board::empty_board();
+            for y in 0..3 {
+                board.fill(&Point::new(3, y));
+            }
+            let game = Game {
+                board,
+                piece: Some(Piece {
+                    kind: PieceKind::O,
+                    orientation: Orientation::North,
+                    position: Point::new(2, 2),
+                }),
+                ..Game::initial()
+            };
+
+            let next_game = game
+                .with_moved_piece(&config, &Move::Translate(Direction::Right))
+                .unwrap();
+
+            assert_eq!(next_game.piece.unwrap().position, Point::new(3, -1));
+        }
+
+        #[test]
+        fn piece_sinks_after_rotation() {
+            let config = Config {
+                kick: Kick::ARS,
+                ..twenty_gravity_config()
+            };
+            let game = Game {
+                piece: Some(Piece {
+                    kind: PieceKind::I,
+                    orientation: Orientation::East,
+                    position: Point::new(3, 0),
+                }),
+                ..Game::initial()
+            };
+
+            let next_game = game
+                .with_moved_piece(&config, &Move::Rotate(Rotation::Clockwise))
+                .unwrap();
+
+            let piece = next_game.piece.unwrap();
+            assert_eq!(piece.orientation, Orientation::South);
+            assert_eq!(piece.position, Point::new(3, -2));
+        }
+
+        #[test]
+        fn t_spin_after_sinking() {
+            let config = twenty_gravity_config();
+            let mut board = Board::empty_board();
+            board.fill(&Point::new(1, 0));
+            board.fill(&Point::new(2, 2));
+            board.fill(&Point::new(0, 4));
+            board.fill(&Point::new(2, 4));
+            let game = Game {
+                board,
+                piece: Some(Piece {
+                    kind: PieceKind::T,
+                    orientation: Orientation::North,
+                    position: Point::new(0, 3),
+                }),
+                ..Game::initial()
+            };
+
+            let next_game = game
+                .reduce(&config, &Action::Move(Move::Rotate(Rotation::Clockwise)))
+                .unwrap();
+
+            // The fourth kick lands a line above the floor, and the piece sinks into the slot.
+            let piece = next_game.piece.unwrap();
+            assert_eq!(piece.orientation, Orientation::East);
+            assert_eq!(piece.position, Point::new(-1, 0));
+            assert_eq!(
+                next_game.last_move,
+                Some(LastMove {
+                    mov: Move::Rotate(Rotation::Clockwise),
+                    kick_index: Some(3),
+                })
+            );
+            assert_eq!(next_game.get_spin(&config), Some(Spin::TSpin));
+        }
+
+        #[test]
+        fn piece_sinks_after_hold() {
+            let config = twenty_gravity_config();
+            let game = Game {
+                hold_kind: Some(PieceKind::O),
+                piece: Some(Piece::spawn(&config, &PieceKind::T)),
+                ..Game::initial()
+            };
+
+            let next_game = game
+                .reduce(&config, &Action::Hold { switch: true })
+                .unwrap();
+
+            assert_eq!(next_game.piece.unwrap().position, Point::new(3, -1));
+        }
+
+        #[test]
+        fn no_drop_moves() {
+            let config = Config {
+                soft_drop_allowed: true,
+                ..twenty_gravity_config()
+            };
+
+            let moves = config.possible_moves();
+
+            assert!(!moves.contains(&Move::Drop));
+            assert!(!moves.contains(&Move::Translate(Direction::Down)));
+        }
+    }
+
+    mod with_spawned_piece {
+        use crate::utils::rotation::Orientation;
+
+        use super::*;
+
+        fn config_with_initial_actions() -> Config {
+            Config {
+                initial_rotation: true,
+                initial_hold: true,
+                ..Config::default()
+            }
+        }
+
+        #[test]
+        fn spawns_piece() {
+            let next_game = Game::initial()
+                .with_spawned_piece(&CONFIG, &PieceKind::T, &InitialActions::NONE)
+                .unwrap();
+
+            assert_eq!(next_game.piece, Some(Piece::spawn(&CONFIG, &PieceKind::T)));
+            assert!(!next_game.is_hold_used);
+        }
+
+        #[test]
+        fn initial_actions_not_allowed_by_default() {
+            let game = Game {
+                hold_kind: Some(PieceKind::I),
+                ..Game::initial()
+            };
+
+            let rotated_game = game.with_spawned_piece(
+                &CONFIG,
+                &PieceKind::T,
+                &InitialActions {
+                    rotation: Some(Rotation::Clockwise),
+                    hold: false,
+                },
+            );
+            let held_game = game.with_spawned_piece(
+                &CONFIG,
+                &PieceKind::T,
+                &InitialActions {
+                    rotation: None,
+                    hold: true,
+                },
+            );
+
+            assert_eq!(rotated_game, Err(SpawnError::InitialRotationNotAllowed));
+            assert_eq!(held_game, Err(SpawnError::InitialHoldNotAllowed));
+        }
+
+        #[test]
+        fn half_rotation_not_allowed_without_half_rotations() {
+            let next_game = Game::initial().with_spawned_piece(
+                &config_with_initial_actions(),
+                &PieceKind::T,
+                &InitialActions {
+                    rotation: Some(Rotation::Half),
+                    hold: false,
+                },
+            );
+
+            assert_eq!(next_game, Err(SpawnError::InitialRotationNotAllowed));
+        }
+
+        #[test]
+        fn spawns_rotated() {
+            let config = config_with_initial_actions();
+
+            let next_game = Game::initial()
+                .with_spawned_piece(
+                    &config,
+                    &PieceKind::T,
+                    &InitialActions {
+                        rotation: Some(Rotation::AntiClockwise),
+                        hold: false,
+                    },
+                )
+                .unwrap();
+
+            let piece = next_game.piece.unwrap();
+            assert_eq!(piece.orientation, Orientation::West);
+            assert_eq!(piece.position, PieceKind::T.get_spawn_point(&config));
+        }
+
+        #[test]
+        fn spawns_unrotated_if_rotated_piece_does_not_fit() {
+            let config = config_with_initial_actions();
+            let mut board = Board::empty_board();
+            board.fill(&Point::new(4, 19));
+
+            let next_game = Game {
+                board,
+                ..Game::initial()
+            }
+            .with_spawned_piece(
+                &config,
+                &PieceKind::T,
+                &InitialActions {
+                    rotation: Some(Rotation::Clockwise),
+                    hold: false,
+                },
+            )
+            .unwrap();
+
+            assert_eq!(next_game.piece, Some(Piece::spawn(&config, &PieceKind::T)));
+        }
+
+        #[test]
+        fn spawns_rotated_if_unrotated_piece_does_not_fit() {
+            let config = config_with_initial_actions();
+            let mut board = Board::empty_board();
+            board.fill(&Point::new(3, 20));
+
+            let next_game = Game {
+                board,
+                ..Game::initial()
+            }
+            .with_spawned_piece(
+                &config,
+                &PieceKind::T,
+                &InitialActions {
+                    rotation: Some(Rotation::Clockwise),
+                    hold: false,
+                },
+            )
+            .unwrap();
+
+            assert_eq!(next_game.piece.unwrap().orientation, Orientation::East);
+        }
+
+        #[test]
+        fn invalid_if_no_piece_fits() {
+            let mut board = Board::empty_board();
+            board.fill(&Point::new(4, 20));
+
+            let next_game = Game {
+                board,
+                ..Game::initial()
+            }
+            .with_spawned_piece(
+                &config_with_initial_actions(),
+                &PieceKind::T,
+                &InitialActions {
+                    rotation: Some(Rotation::Clockwise),
+                    hold: false,
+                },
+            );
+
+            assert_eq!(next_game, Err(SpawnError::PieceCollision));
+        }
+
+        #[test]
+        fn initial_hold_swaps_hold() {
+            let game = Game {
+                hold_kind: Some(PieceKind::I),
+                ..Game::initial()
+            };
+
+            let next_game = game
+                .with_spawned_piece(
+                    &config_with_initial_actions(),
+                    &PieceKind::T,
+                    &InitialActions {
+                        rotation: Some(Rotation::Clockwise),
+                        hold: true,
+                    },
+                )
+                .unwrap();
+
+            let piece = next_game.piece.unwrap();
+            assert_eq!(piece.kind, PieceKind::I);
+            assert_eq!(piece.orientation, Orientation::East);
+            assert_eq!(next_game.hold_kind, Some(PieceKind::T));
+            assert!(next_game.is_hold_used);
+        }
+
+        #[test]
+        fn invalid_initial_hold_if_no_hold_piece() {
+            let next_game = Game::initial().with_spawned_piece(
+                &config_with_initial_actions(),
+                &PieceKind::T,
+                &InitialActions {
+                    rotation: None,
+                    hold: true,
+                },
+            );
+
+            assert_eq!(next_game, Err(SpawnError::NoHoldPiece));
+        }
+    }
+
+    mod with_hold_used {
+        use crate::config::Spawn;
+
+        use super::*;
+
+        #[test]
+        fn invalid_if_no_active_piece() {
+            let game = Game {
+                hold_kind: Some(PieceKind::J),
+                ..Game::initial()
+            };
+
+            let next_game = game.reduce(&CONFIG, &Action::Hold { switch: true });
+
+            assert_eq!(next_game, Err(ReduceError::Hold(HoldError::NoPiece)));
+        }
+
+        #[test]
+        fn holds_into_empty_hold() {
+            let game = Game {
+                piece: Some(Piece::spawn(&CONFIG, &PieceKind::I)),
+                queue: queue_of(&[PieceKind::J]),
+                ..Game::initial()
+            };
+
+            let next_game = game
+                .reduce(&CONFIG, &Action::Hold { switch: true })
+                .unwrap();
+
+            assert!(next_game.is_hold_used);
+            assert_eq!(next_game.hold_kind, Some(PieceKind::I));
+            assert_eq!(next_game.piece, None);
+
+            let spawned_game = next_game
+                .with_spawned_piece(&CONFIG, &PieceKind::J, &InitialActions::NONE)
+                .unwrap();
+
+            assert!(spawned_game.is_hold_used);
+            assert_eq!(
+                spawned_game.reduce(&CONFIG, &Action::Hold { switch: true }),
+                Err(ReduceError::Hold(HoldError::NotAvailable))
+            );
+        }
+
+        #[test]
+        fn invalid_if_new_piece_intersects_board() {
+            let mut board = Board::empty_board();
+            for x in 3..7 {
+                board.fill(&Point::new(x, 20));
+            }
+
+            let game = Game {
+                board,
+                hold_kind: Some(PieceKind::I),
+                piece: Some(Piece::spawn(&CONFIG, &PieceKind::J)),
                 ..Game::initial()
             };
 
@@ -678,6 +1796,36 @@ mod tests {
             )
         }
 
+        #[test]
+        fn spawns_above_stack() {
+            let config = Config {
+                spawn: Spawn {
+                    above_stack: true,
+                    ..Spawn::default()
+                },
+                ..Config::default()
+            };
+            let mut board = Board::empty_board();
+            for x in 3..7 {
+                board.fill(&Point::new(x, 20));
+            }
+
+            let game = Game {
+                board,
+                hold_kind: Some(PieceKind::I),
+                piece: Some(Piece::spawn(&config, &PieceKind::J)),
+                ..Game::initial()
+            };
+
+            let next_game = game
+                .reduce(&config, &Action::Hold { switch: true })
+                .unwrap();
+
+            let piece = next_game.piece.unwrap();
+            assert_eq!(piece.kind, PieceKind::I);
+            assert_eq!(piece.position, Point::new(3, 19));
+        }
+
         #[test]
         fn consumes_hold_and_swaps_hold() {
             let game = Game {
@@ -716,6 +1864,8 @@ mod tests {
     }
 
     mod with_placed_piece {
+        use crate::utils::rotation::Orientation;
+
         use super::*;
 
         #[test]
@@ -799,5 +1949,403 @@ mod tests {
                 "Hold should be reset after placing piece"
             );
         }
+
+        #[test]
+        fn outcome_without_line_clear() {
+            let game = Game {
+                piece: Some(Piece {
+                    position: Point::new(3, -2),
+                    ..Piece::spawn(&CONFIG, &PieceKind::I)
+                }),
+                ..Game::initial()
+            };
+
+            let (_, outcome) = game.reduce_with_outcome(&CONFIG, &Action::Place).unwrap();
+
+            assert_eq!(
+                outcome,
+                Some(PlaceOutcome {
+                    cleared_rows: vec![],
+                    is_perfect_clear: false,
+                    spin: None,
+                })
+            );
+        }
+
+        #[test]
+        fn outcome_with_line_clear() {
+            let mut board = Board::empty_board();
+            for x in 0..Board::WIDTH {
+                if x != 9 {
+                    board.fill(&Point::new(x, 1));
+                    board.fill(&Point::new(x, 3));
+                }
+            }
+            board.fill(&Point::new(0, 0));
+            board.fill(&Point::new(0, 2));
+            let game = Game {
+                board,
+                piece: Some(Piece {
+                    orientation: Orientation::East,
+                    position: Point::new(7, 0),
+                    ..Piece::spawn(&CONFIG, &PieceKind::I)
+                }),
+                ..Game::initial()
+            };
+
+            let (next_game, outcome) = game.reduce_with_outcome(&CONFIG, &Action::Place).unwrap();
+
+            let outcome = outcome.unwrap();
+            assert_eq!(outcome.cleared_rows, vec![1, 3]);
+            assert_eq!(outcome.lines_cleared(), 2);
+            assert!(!outcome.is_perfect_clear);
+            assert_eq!(next_game.board.stack_height(), 2);
+        }
+
+        #[test]
+        fn outcome_with_perfect_clear() {
+            let mut board = Board::empty_board();
+            for x in 0..Board::WIDTH - 4 {
+                board.fill(&Point::new(x, 0));
+            }
+            let game = Game {
+                board,
+                piece: Some(Piece {
+                    position: Point::new(6, -2),
+                    ..Piece::spawn(&CONFIG, &PieceKind::I)
+                }),
+                ..Game::initial()
+            };
+
+            let (_, outcome) = game.reduce_with_outcome(&CONFIG, &Action::Place).unwrap();
+
+            let outcome = outcome.unwrap();
+            assert_eq!(outcome.cleared_rows, vec![0]);
+            assert!(outcome.is_perfect_clear);
+        }
+
+        #[test]
+        fn no_outcome_without_placement() {
+            let game = Game {
+                piece: Some(Piece::spawn(&CONFIG, &PieceKind::I)),
+                ..Game::initial()
+            };
+
+            let (_, outcome) = game
+                .reduce_with_outcome(&CONFIG, &Action::Move(Move::Drop))
+                .unwrap();
+
+            assert_eq!(outcome, None);
+        }
+
+        #[test]
+        fn updates_score() {
+            let mut board = Board::empty_board();
+            for x in 0..Board::WIDTH - 4 {
+                board.fill(&Point::new(x, 0));
+                board.fill(&Point::new(x, 1));
+            }
+            let piece = Piece {
+                position: Point::new(6, -2),
+                ..Piece::spawn(&CONFIG, &PieceKind::I)
+            };
+            let game = Game {
+                board,
+                piece: Some(piece),
+                score: Some(Score::initial()),
+                ..Game::initial()
+            };
+
+            let next_game = game.reduce(&CONFIG, &Action::Place).unwrap();
+
+            assert_eq!(
+                next_game.score,
+                Some(Score {
+                    points: 100,
+                    combo: 1,
+                    ..Score::initial()
+                })
+            );
+        }
+
+        #[test]
+        fn unscored_game() {
+            let game = Game {
+                piece: Some(Piece {
+                    position: Point::new(3, -2),
+                    ..Piece::spawn(&CONFIG, &PieceKind::I)
+                }),
+                ..Game::initial()
+            };
+
+            let next_game = game.reduce(&CONFIG, &Action::Place).unwrap();
+
+            assert_eq!(next_game.score, None);
+        }
+    }
+
+    mod with_spin {
+        use crate::utils::rotation::Orientation;
+
+        use super::*;
+
+        fn rotated_game(board: Board, piece: Piece, kick_index: Option<usize>) -> Game {
+            Game {
+                board,
+                piece: Some(piece),
+                last_move: Some(LastMove {
+                    mov: Move::Rotate(Rotation::Clockwise),
+                    kick_index,
+                }),
+                ..Game::initial()
+            }
+        }
+
+        /// A T-spin double slot with an overhang on the left.
+        fn t_slot_board() -> Board {
+            let mut board = Board::empty_board();
+            for x in 0..Board::WIDTH {
+                if x != 4 {
+                    board.fill(&Point::new(x, 0));
+                }
+                if !(3..=5).contains(&x) {
+                    board.fill(&Point::new(x, 1));
+                }
+            }
+            board.fill(&Point::new(3, 2));
+            board
+        }
+
+        fn t_piece_in_slot() -> Piece {
+            Piece {
+                orientation: Orientation::South,
+                position: Point::new(3, 0),
+                ..Piece::spawn(&CONFIG, &PieceKind::T)
+            }
+        }
+
+        /// A T piece against the left wall, pointing right with one front corner filled.
+        fn t_mini_game(kick_index: Option<usize>) -> Game {
+            let mut board = Board::empty_board();
+            board.fill(&Point::new(1, 0));
+            let piece = Piece {
+                orientation: Orientation::East,
+                position: Point::new(-1, 0),
+                ..Piece::spawn(&CONFIG, &PieceKind::T)
+            };
+            rotated_game(board, piece, kick_index)
+        }
+
+        /// A horizontal I piece in a tunnel under the stack.
+        fn i_tunnel_board() -> Board {
+            let mut board = Board::empty_board();
+            for x in 0..Board::WIDTH {
+                if !(3..=6).contains(&x) {
+                    board.fill(&Point::new(x, 0));
+                }
+                board.fill(&Point::new(x, 1));
+            }
+            board
+        }
+
+        fn i_piece_in_tunnel() -> Piece {
+            Piece {
+                position: Point::new(3, -2),
+                ..Piece::spawn(&CONFIG, &PieceKind::I)
+            }
+        }
+
+        #[test]
+        fn rotation_records_kick_index() {
+            let game = Game {
+                piece: Some(Piece {
+                    position: Point::new(3, -1),
+                    ..Piece::spawn(&CONFIG, &PieceKind::T)
+                }),
+                ..Game::initial()
+            };
+
+            let next_game = game
+                .reduce(&CONFIG, &Action::Move(Move::Rotate(Rotation::Clockwise)))
+                .unwrap();
+
+            assert_eq!(
+                next_game.last_move,
+                Some(LastMove {
+                    mov: Move::Rotate(Rotation::Clockwise),
+                    kick_index: Some(1),
+                })
+            );
+
+            let next_game = next_game
+                .reduce(&CONFIG, &Action::Move(Move::Translate(Direction::Right)))
+                .unwrap();
+
+            assert_eq!(
+                next_game.last_move,
+                Some(LastMove {
+                    mov: Move::Translate(Direction::Right),
+                    kick_index: None,
+                })
+            );
+        }
+
+        #[test]
+        fn t_spin() {
+            let game = rotated_game(t_slot_board(), t_piece_in_slot(), None);
+
+            assert_eq!(game.get_spin(&CONFIG), Some(Spin::TSpin));
+        }
+
+        #[test]
+        fn t_spin_mini() {
+            let game = t_mini_game(Some(0));
+
+            assert_eq!(game.get_spin(&CONFIG), Some(Spin::TSpinMini));
+        }
+
+        #[test]
+        fn triple_kick_upgrades_t_spin_mini() {
+            let game = t_mini_game(Some(3));
+
+            assert_eq!(game.get_spin(&CONFIG), Some(Spin::TSpin));
+        }
+
+        #[test]
+        fn no_t_spin_with_two_corners() {
+            let mut board = t_slot_board();
+            board.empty(&Point::new(3, 2));
+            let game = rotated_game(board, t_piece_in_slot(), None);
+
+            assert_eq!(game.get_spin(&CONFIG), None);
+        }
+
+        #[test]
+        fn no_spin_without_rotation() {
+            let game = Game {
+                last_move: Some(LastMove {
+                    mov: Move::Translate(Direction::Left),
+                    kick_index: None,
+                }),
+                ..rotated_game(t_slot_board(), t_piece_in_slot(), None)
+            };
+
+            assert_eq!(game.get_spin(&CONFIG), None);
+        }
+
+        #[test]
+        fn all_spin() {
+            let game = rotated_game(i_tunnel_board(), i_piece_in_tunnel(), None);
+
+            assert_eq!(game.get_spin(&CONFIG), Some(Spin::AllSpin));
+        }
+
+        #[test]
+        fn no_all_spin_for_o_piece() {
+            let piece = Piece::spawn(&CONFIG, &PieceKind::O);
+            let mut board = Board::filled_board();
+            for point in piece.get_points(&CONFIG) {
+                board.empty(&point);
+            }
+            let game = rotated_game(board, piece, None);
+
+            assert_eq!(game.get_spin(&CONFIG), None);
+        }
+
+        #[test]
+        fn no_all_spin_if_piece_can_move_up() {
+            let mut board = i_tunnel_board();
+            for x in 3..=6 {
+                board.empty(&Point::new(x, 1));
+            }
+            let game = rotated_game(board, i_piece_in_tunnel(), None);
+
+            assert_eq!(game.get_spin(&CONFIG), None);
+        }
+
+        #[test]
+        fn placement_records_spin() {
+            let game = rotated_game(t_slot_board(), t_piece_in_slot(), None);
+
+            let (next_game, outcome) = game.with_placed_piece(&CONFIG).unwrap();
+
+            assert_eq!(outcome.spin, Some(Spin::TSpin));
+            assert_eq!(outcome.cleared_rows, vec![0, 1]);
+            assert_eq!(next_game.last_move, None);
+        }
+    }
+
+    mod with_received_garbage {
+        use super::*;
+
+        fn receive_garbage(line_count: u8, hole_column: isize) -> Action {
+            Action::ReceiveGarbage {
+                line_count,
+                hole_column,
+            }
+        }
+
+        #[test]
+        fn pushes_garbage_under_stack() {
+            let mut board = Board::empty_board();
+            board.fill(&Point::new(5, 0));
+            let game = Game {
+                board,
+                piece: Some(Piece::spawn(&CONFIG, &PieceKind::T)),
+                ..Game::initial()
+            };
+
+            let next_game = game.reduce(&CONFIG, &receive_garbage(2, 9)).unwrap();
+
+            let mut expected_board = board;
+            expected_board.push_garbage(2, 9);
+            assert_eq!(next_game.board, expected_board);
+            assert!(next_game.board.is_filled(&Point::new(5, 2)));
+            assert_eq!(next_game.piece, game.piece);
+        }
+
+        #[test]
+        fn invalid_hole_column() {
+            let game = Game::initial();
+
+            for hole_column in [-1, Board::WIDTH] {
+                assert_eq!(
+                    game.reduce(&CONFIG, &receive_garbage(1, hole_column)),
+                    Err(ReduceError::Garbage(GarbageError::InvalidHoleColumn))
+                );
+            }
+        }
+
+        #[test]
+        fn topped_out() {
+            let mut board = Board::empty_board();
+            board.fill(&Point::new(0, Board::HEIGHT - 3));
+            let game = Game {
+                board,
+                ..Game::initial()
+            };
+
+            assert!(game.reduce(&CONFIG, &receive_garbage(2, 0)).is_ok());
+            assert_eq!(
+                game.reduce(&CONFIG, &receive_garbage(3, 0)),
+                Err(ReduceError::Garbage(GarbageError::ToppedOut))
+            );
+        }
+
+        #[test]
+        fn piece_collision() {
+            let game = Game {
+                piece: Some(Piece {
+                    position: Point::new(3, -2),
+                    ..Piece::spawn(&CONFIG, &PieceKind::I)
+                }),
+                ..Game::initial()
+            };
+
+            assert_eq!(
+                game.reduce(&CONFIG, &receive_garbage(1, 0)),
+                Err(ReduceError::Garbage(GarbageError::PieceCollision))
+            );
+        }
     }
 }
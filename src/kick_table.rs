@@ -0,0 +1,307 @@
+use crate::piece::PieceKind;
+use crate::utils::point::Point;
+use crate::utils::rotation::Orientation;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/**
+A user-defined kick table, for rotation systems which are not built in.
+
+Tables are loaded from text with one transition per line. Each line lists the piece kinds, the
+orientation rotated from and to, then the kicks to try in order. Orientations are written as `N`,
+`E`, `S`, `W` or in SRS notation as `0`, `R`, `2`, `L`. Any text after a `#` is ignored.
+
+```text
+# SRS kicks for rotating clockwise from spawn.
+JLSTZ N E: -1,0 -1,1 0,-2 -1,-2
+I     N E: -2,0 1,0 -2,-1 1,2
+```
+
+Transitions which are not listed, or are listed without kicks, only succeed if the piece fits
+without a kick. This includes 180 rotations, which never fall back to `Config::half_rotation`'s
+kicks.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct KickTable {
+    kicks: HashMap<(PieceKind, Orientation, Orientation), Vec<Point>>,
+}
+
+impl KickTable {
+    pub fn new() -> KickTable {
+        KickTable {
+            kicks: HashMap::new(),
+        }
+    }
+
+    pub fn get(
+        &self,
+        piece_kind: &PieceKind,
+        from: &Orientation,
+        to: &Orientation,
+    ) -> Option<&[Point]> {
+        self.kicks
+            .get(&(*piece_kind, *from, *to))
+            .map(|kicks| kicks.as_slice())
+    }
+}
+
+impl Default for KickTable {
+    fn default() -> Self {
+        KickTable::new()
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum KickTableError {
+    /// The line is not of the form `<piece kinds> <from> <to>: <kicks>`.
+    MalformedLine {
+        line: usize,
+    },
+    UnknownPieceKind {
+        line: usize,
+        transition: String,
+    },
+    UnknownOrientation {
+        line: usize,
+        transition: String,
+    },
+    /// The transition rotates to the orientation it starts from.
+    SameOrientation {
+        line: usize,
+        transition: String,
+    },
+    /// A kick is not of the form `<x>,<y>`.
+    MalformedKick {
+        line: usize,
+        transition: String,
+        kick: String,
+    },
+    /// The transition was already defined for one of its piece kinds.
+    DuplicateTransition {
+        line: usize,
+        transition: String,
+    },
+}
+
+impl FromStr for KickTable {
+    type Err = KickTableError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut table = KickTable::new();
+
+        for (line_idx, raw_line) in text.lines().enumerate() {
+            let line = line_idx + 1;
+            let content = raw_line.split('#').next().unwrap_or_default().trim();
+            if content.is_empty() {
+                continue;
+            }
+
+            let Some((transition, kicks)) = content.split_once(':') else {
+                return Err(KickTableError::MalformedLine { line });
+            };
+            let fields = transition.split_whitespace().collect::<Vec<_>>();
+            let [piece_kinds, from, to] = fields[..] else {
+                return Err(KickTableError::MalformedLine { line });
+            };
+            let transition = fields.join(" ");
+
+            let Some(piece_kinds) = piece_kinds
+                .chars()
+                .map(parse_piece_kind)
+                .collect::<Option<Vec<_>>>()
+            else {
+                return Err(KickTableError::UnknownPieceKind { line, transition });
+            };
+            let (Some(from), Some(to)) = (parse_orientation(from), parse_orientation(to)) else {
+                return Err(KickTableError::UnknownOrientation { line, transition });
+            };
+            if from == to {
+                return Err(KickTableError::SameOrientation { line, transition });
+            }
+
+            let mut offsets = vec![];
+            for kick in kicks.split_whitespace() {
+                let Some(offset) = parse_kick(kick) else {
+                    return Err(KickTableError::MalformedKick {
+                        line,
+                        transition,
+                        kick: kick.to_string(),
+                    });
+                };
+                offsets.push(offset);
+            }
+
+            for piece_kind in piece_kinds {
+                let key = (piece_kind, from, to);
+                if table.kicks.contains_key(&key) {
+                    return Err(KickTableError::DuplicateTransition { line, transition });
+                }
+                table.kicks.insert(key, offsets.clone());
+            }
+        }
+
+        Ok(table)
+    }
+}
+
+fn parse_piece_kind(c: char) -> Option<PieceKind> {
+    match c {
+        'I' => Some(PieceKind::I),
+        'J' => Some(PieceKind::J),
+        'L' => Some(PieceKind::L),
+        'O' => Some(PieceKind::O),
+        'S' => Some(PieceKind::S),
+        'T' => Some(PieceKind::T),
+        'Z' => Some(PieceKind::Z),
+        _ => None,
+    }
+}
+
+fn parse_orientation(s: &str) -> Option<Orientation> {
+    match s {
+        "N" | "0" => Some(Orientation::North),
+        "E" | "R" => Some(Orientation::East),
+        "S" | "2" => Some(Orientation::South),
+        "W" | "L" => Some(Orientation::West),
+        _ => None,
+    }
+}
+
+fn parse_kick(s: &str) -> Option<Point> {
+    let (x, y) = s.split_once(',')?;
+    Some(Point::new(x.parse().ok()?, y.parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod from_str {
+        use super::*;
+
+        #[test]
+        fn parses_kicks_for_each_piece_kind() {
+            let table = "JLSTZ N E: -1,0 -1,1 0,-2 -1,-2"
+                .parse::<KickTable>()
+                .unwrap();
+
+            for piece_kind in [
+                PieceKind::J,
+                PieceKind::L,
+                PieceKind::S,
+                PieceKind::T,
+                PieceKind::Z,
+            ] {
+                assert_eq!(
+                    table.get(&piece_kind, &Orientation::North, &Orientation::East),
+                    Some(
+                        &[
+                            Point::new(-1, 0),
+                            Point::new(-1, 1),
+                            Point::new(0, -2),
+                            Point::new(-1, -2),
+                        ][..]
+                    )
+                );
+            }
+            assert_eq!(
+                table.get(&PieceKind::I, &Orientation::North, &Orientation::East),
+                None
+            );
+            assert_eq!(
+                table.get(&PieceKind::T, &Orientation::East, &Orientation::North),
+                None
+            );
+        }
+
+        #[test]
+        fn parses_any_number_of_kicks() {
+            let table = "
+                # Comments and blank lines are skipped.
+                T 0 R: 1,0 2,0 3,0 4,0 5,0 6,0 # Trailing comments too.
+                T R 0:
+            "
+            .parse::<KickTable>()
+            .unwrap();
+
+            assert_eq!(
+                table
+                    .get(&PieceKind::T, &Orientation::North, &Orientation::East)
+                    .map(|kicks| kicks.len()),
+                Some(6)
+            );
+            assert_eq!(
+                table.get(&PieceKind::T, &Orientation::East, &Orientation::North),
+                Some(&[][..])
+            );
+        }
+
+        #[test]
+        fn malformed_line() {
+            assert_eq!(
+                "T N E: 1,0\nT N S".parse::<KickTable>(),
+                Err(KickTableError::MalformedLine { line: 2 })
+            );
+            assert_eq!(
+                "T N: 1,0".parse::<KickTable>(),
+                Err(KickTableError::MalformedLine { line: 1 })
+            );
+        }
+
+        #[test]
+        fn unknown_piece_kind() {
+            assert_eq!(
+                "TX N E: 1,0".parse::<KickTable>(),
+                Err(KickTableError::UnknownPieceKind {
+                    line: 1,
+                    transition: "TX N E".to_string(),
+                })
+            );
+        }
+
+        #[test]
+        fn unknown_orientation() {
+            assert_eq!(
+                "T N Q: 1,0".parse::<KickTable>(),
+                Err(KickTableError::UnknownOrientation {
+                    line: 1,
+                    transition: "T N Q".to_string(),
+                })
+            );
+        }
+
+        #[test]
+        fn same_orientation() {
+            assert_eq!(
+                "T E R: 1,0".parse::<KickTable>(),
+                Err(KickTableError::SameOrientation {
+                    line: 1,
+                    transition: "T E R".to_string(),
+                })
+            );
+        }
+
+        #[test]
+        fn malformed_kick() {
+            assert_eq!(
+                "T N E: 1,0 1;1".parse::<KickTable>(),
+                Err(KickTableError::MalformedKick {
+                    line: 1,
+                    transition: "T N E".to_string(),
+                    kick: "1;1".to_string(),
+                })
+            );
+        }
+
+        #[test]
+        fn duplicate_transition() {
+            assert_eq!(
+                "JLT N E: 1,0\nT 0 R: 2,0".parse::<KickTable>(),
+                Err(KickTableError::DuplicateTransition {
+                    line: 2,
+                    transition: "T 0 R".to_string(),
+                })
+            );
+        }
+    }
+}
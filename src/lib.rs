@@ -6,10 +6,18 @@
 #[global_allocator]
 static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
 
-mod board;
-mod config;
-mod game;
-mod piece;
-mod solver;
-mod state;
-mod utils;
+pub mod attack;
+pub mod board;
+pub mod config;
+pub mod game;
+pub mod kick_table;
+pub mod move_generator;
+pub mod piece;
+pub mod randomizer;
+pub mod scoring;
+pub mod solution;
+pub mod solver;
+pub mod state;
+#[cfg(test)]
+mod test_utils;
+pub mod utils;
@@ -0,0 +1,490 @@
+use crate::board::Board;
+use crate::config::{Config, Gravity};
+use crate::game::{Game, LastMove, Move, Spin};
+use crate::piece::Piece;
+use crate::utils::point::Point;
+use crate::utils::rotation::{Orientation, Rotation};
+use std::collections::{HashMap, VecDeque};
+
+/// A position and orientation the piece can lock into, paired with the fewest moves needed to
+/// reach it from where the piece started with the same spin.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LockPosition {
+    pub piece: Piece,
+    pub moves: Vec<Move>,
+
+    /// The spin the piece locks with after its last move.
+    pub spin: Option<Spin>,
+}
+
+/// The lock positions of a piece, where positions which lock into the same cells with the same
+/// spin are merged.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LockPositions {
+    pub lock_positions: Vec<LockPosition>,
+
+    /// The number of distinct positions, orientations and spins the piece can lock into, before
+    /// those which lock into the same cells are merged.
+    pub raw_count: usize,
+}
+
+impl LockPositions {
+    /// The number of lock positions which fill distinct cells or spin differently.
+    pub fn distinct_count(&self) -> usize {
+        self.lock_positions.len()
+    }
+}
+
+/// A position and orientation, with the spin the piece would lock with there. The same position
+/// can be reached with and without a spin, and every move after it is the same either way.
+type PieceKey = (Point, Orientation, Option<Spin>);
+
+struct PieceKeyValue {
+    is_placable: bool,
+    /// The key and move that reach this key with the fewest inputs, or `None` for the start key.
+    previous: Option<(PieceKey, Move)>,
+}
+
+/// Find every position and orientation `piece` can lock into on `board`. Positions which lock into
+/// the same cells with the same spin, such as an I piece facing north or south, only keep the one
+/// with the fewest moves.
+///
+/// Each piece position, orientation and spin is memoized with the move that reaches it from its
+/// previous key. Searching breadth-first means the first move found to each key is on a path with
+/// the fewest inputs.
+pub fn generate_lock_positions(config: &Config, board: &Board, piece: &Piece) -> LockPositions {
+    let raw_lock_positions = generate_raw_lock_positions(config, board, piece);
+    let raw_count = raw_lock_positions.len();
+
+    let mut index_for_cells: HashMap<(Board, Option<Spin>), usize> = HashMap::new();
+    let mut lock_positions: Vec<LockPosition> = vec![];
+    for lock_position in raw_lock_positions {
+        let mut cells = Board::empty_board();
+        cells.fill_mask(&lock_position.piece.get_mask(config));
+        match index_for_cells.get(&(cells, lock_position.spin)) {
+            Some(&idx) => {
+                if lock_position.moves.len() < lock_positions[idx].moves.len() {
+                    lock_positions[idx] = lock_position;
+                }
+            }
+            None => {
+                index_for_cells.insert((cells, lock_position.spin), lock_positions.len());
+                lock_positions.push(lock_position);
+            }
+        }
+    }
+
+    LockPositions {
+        lock_positions,
+        raw_count,
+    }
+}
+
+/// Find every distinct position, orientation and spin `piece` can lock into on `board`.
+fn generate_raw_lock_positions(config: &Config, board: &Board, piece: &Piece) -> Vec<LockPosition> {
+    let (memo, visit_order) = search_piece_keys(config, board, piece);
+
+    let with_key = |(position, orientation, _): PieceKey| Piece {
+        position,
+        orientation,
+        ..*piece
+    };
+
+    if config.soft_drop_allowed {
+        return visit_order
+            .into_iter()
+            .filter(|key| memo[key].is_placable)
+            .map(|key| LockPosition {
+                piece: with_key(key),
+                moves: get_moves_to_key(&memo, key),
+                spin: key.2,
+            })
+            .collect();
+    }
+
+    // Without soft drop, a drop locks the piece so it can only end a sequence of moves. Keys are
+    // visited in order of fewest inputs, so the first key that drops into a placement is kept.
+    let mut lock_positions: Vec<LockPosition> = vec![];
+    for key in visit_order {
+        let hovering_piece = with_key(key);
+        let (locked_piece, mov, spin) =
+            match get_moved_piece(config, board, &hovering_piece, &Move::Drop) {
+                Some((dropped_piece, _)) => (dropped_piece, Some(Move::Drop), None),
+                None if memo[&key].is_placable => (hovering_piece, None, key.2),
+                None => continue,
+            };
+        if lock_positions
+            .iter()
+            .any(|lock_position| lock_position.piece == locked_piece && lock_position.spin == spin)
+        {
+            continue;
+        }
+        let mut moves = get_moves_to_key(&memo, key);
+        moves.extend(mov);
+        lock_positions.push(LockPosition {
+            piece: locked_piece,
+            moves,
+            spin,
+        });
+    }
+    lock_positions
+}
+
+/// The spin `piece` would lock with on `board` after `mov`, which used the kick at `kick_index`.
+fn get_spin(
+    config: &Config,
+    board: &Board,
+    piece: &Piece,
+    mov: &Move,
+    kick_index: Option<usize>,
+) -> Option<Spin> {
+    if !matches!(mov, Move::Rotate(_)) {
+        return None;
+    }
+    Game {
+        board: *board,
+        piece: Some(*piece),
+        last_move: Some(LastMove {
+            mov: *mov,
+            kick_index,
+        }),
+        ..Game::initial()
+    }
+    .get_spin(config)
+}
+
+/// Breadth-first search every key reachable from `piece`, returning the memo and the keys in the
+/// order they were visited.
+fn search_piece_keys(
+    config: &Config,
+    board: &Board,
+    piece: &Piece,
+) -> (HashMap<PieceKey, PieceKeyValue>, Vec<PieceKey>) {
+    let start_key = (piece.position, piece.orientation, None);
+
+    let mut memo = HashMap::new();
+    memo.insert(
+        start_key,
+        PieceKeyValue {
+            is_placable: board.can_place_mask(&piece.get_mask(config)),
+            previous: None,
+        },
+    );
+    let mut visit_order = vec![start_key];
+    let mut to_visit = VecDeque::from([(*piece, start_key)]);
+
+    // A drop without soft drop locks the piece, so it is handled when finding lock positions.
+    let moves = config
+        .possible_moves()
+        .into_iter()
+        .filter(|&mov| config.soft_drop_allowed || mov != Move::Drop)
+        .collect::<Vec<_>>();
+
+    while let Some((piece, key)) = to_visit.pop_front() {
+        for mov in moves.iter() {
+            let Some((next_piece, kick_index)) = get_moved_piece(config, board, &piece, mov) else {
+                continue;
+            };
+            let spin = get_spin(config, board, &next_piece, mov, kick_index);
+            let next_key = (next_piece.position, next_piece.orientation, spin);
+            if memo.contains_key(&next_key) {
+                continue;
+            }
+            memo.insert(
+                next_key,
+                PieceKeyValue {
+                    is_placable: board.can_place_mask(&next_piece.get_mask(config)),
+                    previous: Some((key, *mov)),
+                },
+            );
+            visit_order.push(next_key);
+            to_visit.push_back((next_piece, next_key));
+        }
+    }
+
+    (memo, visit_order)
+}
+
+/// The piece after `mov`, following the same rules as the game reducer, paired with the index of
+/// the kick used by a rotation. Returns `None` if the move is invalid.
+fn get_moved_piece(
+    config: &Config,
+    board: &Board,
+    piece: &Piece,
+    mov: &Move,
+) -> Option<(Piece, Option<usize>)> {
+    let (moved_piece, kick_index) = match mov {
+        Move::Rotate(rotation) => get_rotated_piece(config, board, piece, rotation),
+        Move::Translate(direction) => {
+            let offset = direction.get_offset();
+            board
+                .can_fit_mask(&piece.get_mask(config).moved(offset))
+                .then(|| {
+                    let moved_piece = Piece {
+                        position: piece.position + offset,
+                        ..*piece
+                    };
+                    (moved_piece, None)
+                })
+        }
+        Move::Drop => get_dropped_piece(config, board, piece).map(|piece| (piece, None)),
+    }?;
+    let moved_piece = match config.gravity {
+        Gravity::Zero => moved_piece,
+        Gravity::TwentyG => get_dropped_piece(config, board, &moved_piece).unwrap_or(moved_piece),
+    };
+    Some((moved_piece, kick_index))
+}
+
+/// The piece moved to the lowest position it can reach, or `None` if it cannot move down.
+fn get_dropped_piece(config: &Config, board: &Board, piece: &Piece) -> Option<Piece> {
+    let mut dropped_mask = piece.get_mask(config);
+    while board.can_fit_mask(&dropped_mask) {
+        dropped_mask.position.y -= 1;
+    }
+    let dropped_y = dropped_mask.position.y + 1;
+    (dropped_y != piece.position.y).then(|| Piece {
+        position: Point::new(piece.position.x, dropped_y),
+        ..*piece
+    })
+}
+
+/// The piece after `rotation`, paired with the index of the kick used, or `None` if it needed no
+/// kick.
+fn get_rotated_piece(
+    config: &Config,
+    board: &Board,
+    piece: &Piece,
+    rotation: &Rotation,
+) -> Option<(Piece, Option<usize>)> {
+    let to_orientation = piece.orientation.rotated(rotation);
+    let rotated_piece = Piece {
+        orientation: to_orientation,
+        ..*piece
+    };
+    let rotated_mask = rotated_piece.get_mask(config);
+
+    if board.can_fit_mask(&rotated_mask) {
+        return Some((rotated_piece, None));
+    }
+
+    let kicks = config.kick_table(&piece.kind, &piece.orientation, &to_orientation)?;
+    kicks
+        .iter()
+        .position(|&kick| board.can_fit_mask(&rotated_mask.moved(kick)))
+        .map(|kick_index| {
+            let kicked_piece = Piece {
+                position: piece.position + kicks[kick_index],
+                ..rotated_piece
+            };
+            (kicked_piece, Some(kick_index))
+        })
+}
+
+/// Trace back through the memo to get the moves from the start key to `key`.
+fn get_moves_to_key(memo: &HashMap<PieceKey, PieceKeyValue>, key: PieceKey) -> Vec<Move> {
+    let mut moves = vec![];
+    let mut current_key = key;
+    while let Some((previous_key, mov)) = memo[&current_key].previous {
+        moves.push(mov);
+        current_key = previous_key;
+    }
+    moves.reverse();
+    moves
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::{HalfKick, Kick};
+    use crate::game::{Action, Game, InitialActions};
+    use crate::piece::{PieceKind, PIECE_KINDS};
+
+    use super::*;
+
+    const CONFIG: Config = Config::default();
+
+    fn replay_moves(config: &Config, game: &Game, moves: &[Move]) -> Game {
+        moves.iter().fold(game.clone(), |game, &mov| {
+            game.reduce(config, &Action::Move(mov))
+                .expect("Expected every move to be valid")
+        })
+    }
+
+    fn board_with_stack() -> Board {
+        let mut board = Board::empty_board();
+        for (x, y) in [
+            (0, 0),
+            (1, 0),
+            (2, 0),
+            (5, 0),
+            (6, 0),
+            (9, 0),
+            (0, 1),
+            (6, 1),
+            (7, 1),
+            (6, 2),
+        ] {
+            board.fill(&Point::new(x, y));
+        }
+        board
+    }
+
+    mod generate_lock_positions {
+        use super::*;
+
+        #[test]
+        fn i_piece_in_empty_board() {
+            let piece = Piece::spawn(&CONFIG, &PieceKind::I);
+
+            let lock_positions = generate_lock_positions(&CONFIG, &Board::empty_board(), &piece);
+
+            // 10 each for east and west, 7 each for north and south
+            assert_eq!(lock_positions.raw_count, 10 + 10 + 7 + 7);
+            // East and west, and north and south, lock into the same cells.
+            assert_eq!(lock_positions.distinct_count(), 10 + 7);
+        }
+
+        #[test]
+        fn o_piece_in_empty_board() {
+            let piece = Piece::spawn(&CONFIG, &PieceKind::O);
+
+            let lock_positions = generate_lock_positions(&CONFIG, &Board::empty_board(), &piece);
+
+            assert_eq!(lock_positions.distinct_count(), 9);
+            for lock_position in lock_positions.lock_positions {
+                assert_eq!(lock_position.piece.orientation, Orientation::North);
+            }
+        }
+
+        #[test]
+        fn keeps_fewest_moves_among_same_cells() {
+            let piece = Piece::spawn(&CONFIG, &PieceKind::S);
+            let get_cells = |lock_position: &LockPosition| {
+                let mut cells = Board::empty_board();
+                cells.fill_mask(&lock_position.piece.get_mask(&CONFIG));
+                cells
+            };
+
+            let lock_positions = generate_lock_positions(&CONFIG, &Board::empty_board(), &piece);
+
+            let raw_lock_positions =
+                generate_raw_lock_positions(&CONFIG, &Board::empty_board(), &piece);
+            assert!(lock_positions.distinct_count() < raw_lock_positions.len());
+            for lock_position in lock_positions.lock_positions.iter() {
+                let cells = get_cells(lock_position);
+                for raw_lock_position in raw_lock_positions.iter() {
+                    if get_cells(raw_lock_position) == cells {
+                        assert!(lock_position.moves.len() <= raw_lock_position.moves.len());
+                    }
+                }
+            }
+        }
+
+        #[test]
+        fn keeps_spin_into_same_cells() {
+            let config = Config {
+                soft_drop_allowed: true,
+                ..Config::default()
+            };
+            let mut board = Board::empty_board();
+            board.fill(&Point::new(1, 0));
+            let piece = Piece::spawn(&config, &PieceKind::T);
+            let against_wall = Piece {
+                orientation: Orientation::East,
+                position: Point::new(-1, 0),
+                ..piece
+            };
+
+            let lock_positions = generate_lock_positions(&config, &board, &piece);
+
+            let spins = lock_positions
+                .lock_positions
+                .iter()
+                .filter(|lock_position| lock_position.piece == against_wall)
+                .map(|lock_position| lock_position.spin)
+                .collect::<Vec<_>>();
+            assert_eq!(spins.len(), 2);
+            assert!(spins.contains(&None));
+            assert!(spins.contains(&Some(Spin::TSpinMini)));
+        }
+
+        #[test]
+        fn twenty_gravity_cannot_climb_stack() {
+            let mut board = Board::empty_board();
+            for y in 0..4 {
+                board.fill(&Point::new(2, y));
+            }
+            let piece = Piece::spawn(&CONFIG, &PieceKind::O);
+            let twenty_gravity_config = Config {
+                gravity: Gravity::TwentyG,
+                ..Config::default()
+            };
+            let sunk_piece = get_dropped_piece(&twenty_gravity_config, &board, &piece).unwrap();
+            let is_left_of_stack = |lock_position: &LockPosition| {
+                lock_position
+                    .piece
+                    .get_points(&CONFIG)
+                    .iter()
+                    .all(|point| point.x < 2)
+            };
+
+            let lock_positions = generate_lock_positions(&CONFIG, &board, &piece);
+            let twenty_gravity_lock_positions =
+                generate_lock_positions(&twenty_gravity_config, &board, &sunk_piece);
+
+            assert!(lock_positions.lock_positions.iter().any(is_left_of_stack));
+            assert!(!twenty_gravity_lock_positions
+                .lock_positions
+                .iter()
+                .any(is_left_of_stack));
+            assert_eq!(twenty_gravity_lock_positions.distinct_count(), 6);
+        }
+
+        #[test]
+        fn agrees_with_game_reducer() {
+            for (kick, soft_drop_allowed, half_rotation, gravity) in [
+                (Kick::SRS, false, None, Gravity::Zero),
+                (Kick::SRS, true, None, Gravity::Zero),
+                (Kick::SRS, false, Some(HalfKick::TetrIO), Gravity::Zero),
+                (Kick::SRS, true, Some(HalfKick::PPT), Gravity::Zero),
+                (Kick::SRSPlus, false, None, Gravity::Zero),
+                (Kick::ARS, false, None, Gravity::Zero),
+                (Kick::NoKicks, true, None, Gravity::Zero),
+                (
+                    Kick::Custom("JLSTZ N E: 1,0 1,1 0,-2\nI E N: -1,0 2,0".parse().unwrap()),
+                    false,
+                    Some(HalfKick::TetrIO),
+                    Gravity::Zero,
+                ),
+                (Kick::SRS, false, None, Gravity::TwentyG),
+                (Kick::ARS, true, Some(HalfKick::NoKicks), Gravity::TwentyG),
+            ] {
+                let config = Config {
+                    kick,
+                    soft_drop_allowed,
+                    half_rotation,
+                    gravity,
+                    ..Config::default()
+                };
+                for kind in PIECE_KINDS {
+                    let game = Game {
+                        board: board_with_stack(),
+                        ..Game::initial()
+                    }
+                    .with_spawned_piece(&config, &kind, &InitialActions::NONE)
+                    .unwrap();
+
+                    for lock_position in
+                        generate_lock_positions(&config, &game.board, &game.piece.unwrap())
+                            .lock_positions
+                    {
+                        let locked_game = replay_moves(&config, &game, &lock_position.moves);
+                        assert_eq!(locked_game.piece, Some(lock_position.piece));
+                        assert_eq!(locked_game.get_spin(&config), lock_position.spin);
+                        assert!(locked_game.reduce(&config, &Action::Place).is_ok());
+                    }
+                }
+            }
+        }
+    }
+}
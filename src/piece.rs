@@ -1,7 +1,9 @@
-use crate::config::{Config, Kick};
+use crate::board::Board;
+use crate::config::{Config, Kick, SpawnColumn};
 use crate::utils::point::Point;
 use crate::utils::rotation::Orientation;
 use std::convert::TryFrom;
+use std::sync::OnceLock;
 use wasm_bindgen::prelude::*;
 
 #[wasm_bindgen]
@@ -45,16 +47,167 @@ impl TryFrom<u8> for PieceKind {
 }
 
 impl PieceKind {
+    /// The bottom-left corner of the bounding box when the piece spawns, placing the lowest
+    /// cells of the piece on the spawn row and centring the piece on the board.
     pub fn get_spawn_point(&self, config: &Config) -> Point {
+        let offsets = self.get_oriented_offsets(config, &config.spawn.orientation);
+        let min_x = offsets.iter().map(|offset| offset.x).min().unwrap();
+        let max_x = offsets.iter().map(|offset| offset.x).max().unwrap();
+        let min_y = offsets.iter().map(|offset| offset.y).min().unwrap();
+
+        let empty_columns = Board::WIDTH - (max_x - min_x + 1);
+        let left_column = match config.spawn.column {
+            SpawnColumn::Left => empty_columns / 2,
+            SpawnColumn::Right => (empty_columns + 1) / 2,
+        };
+
+        Point::new(
+            left_column - min_x,
+            Board::VISIBLE_HEIGHT + config.spawn.row - min_y,
+        )
+    }
+
+    /// The offsets of each cell from the bottom-left corner of the bounding box in `orientation`.
+    fn get_oriented_offsets(&self, config: &Config, orientation: &Orientation) -> [Point; 4] {
         match config.kick {
-            Kick::SRS => match self {
-                PieceKind::I => Point::new(3, 0),
-                PieceKind::J => Point::new(3, 1),
-                PieceKind::L => Point::new(3, 1),
-                PieceKind::O => Point::new(3, 1),
-                PieceKind::S => Point::new(3, 1),
-                PieceKind::T => Point::new(3, 1),
-                PieceKind::Z => Point::new(3, 1),
+            Kick::ARS => self.get_ars_offsets(orientation),
+            _ => {
+                let mut offset_box = self.get_unoriented_offset_box(config);
+                orient_offset_box(&mut offset_box, orientation);
+                offset_box.offsets
+            }
+        }
+    }
+
+    /// ARS orientations are not symmetric rotations of the bounding box, so each is listed.
+    fn get_ars_offsets(&self, orientation: &Orientation) -> [Point; 4] {
+        let is_vertical = matches!(orientation, Orientation::East | Orientation::West);
+        match self {
+            PieceKind::I => match is_vertical {
+                false => [
+                    Point::new(0, 2),
+                    Point::new(1, 2),
+                    Point::new(2, 2),
+                    Point::new(3, 2),
+                ],
+                true => [
+                    Point::new(2, 0),
+                    Point::new(2, 1),
+                    Point::new(2, 2),
+                    Point::new(2, 3),
+                ],
+            },
+            PieceKind::J => match orientation {
+                Orientation::North => [
+                    Point::new(0, 1),
+                    Point::new(1, 1),
+                    Point::new(2, 1),
+                    Point::new(2, 0),
+                ],
+                Orientation::East => [
+                    Point::new(1, 2),
+                    Point::new(1, 1),
+                    Point::new(1, 0),
+                    Point::new(0, 0),
+                ],
+                Orientation::South => [
+                    Point::new(0, 1),
+                    Point::new(0, 0),
+                    Point::new(1, 0),
+                    Point::new(2, 0),
+                ],
+                Orientation::West => [
+                    Point::new(1, 2),
+                    Point::new(2, 2),
+                    Point::new(1, 1),
+                    Point::new(1, 0),
+                ],
+            },
+            PieceKind::L => match orientation {
+                Orientation::North => [
+                    Point::new(0, 1),
+                    Point::new(1, 1),
+                    Point::new(2, 1),
+                    Point::new(0, 0),
+                ],
+                Orientation::East => [
+                    Point::new(0, 2),
+                    Point::new(1, 2),
+                    Point::new(1, 1),
+                    Point::new(1, 0),
+                ],
+                Orientation::South => [
+                    Point::new(2, 1),
+                    Point::new(0, 0),
+                    Point::new(1, 0),
+                    Point::new(2, 0),
+                ],
+                Orientation::West => [
+                    Point::new(1, 2),
+                    Point::new(1, 1),
+                    Point::new(1, 0),
+                    Point::new(2, 0),
+                ],
+            },
+            PieceKind::O => [
+                Point::new(1, 1),
+                Point::new(2, 1),
+                Point::new(1, 0),
+                Point::new(2, 0),
+            ],
+            PieceKind::S => match is_vertical {
+                false => [
+                    Point::new(1, 1),
+                    Point::new(2, 1),
+                    Point::new(0, 0),
+                    Point::new(1, 0),
+                ],
+                true => [
+                    Point::new(0, 2),
+                    Point::new(0, 1),
+                    Point::new(1, 1),
+                    Point::new(1, 0),
+                ],
+            },
+            PieceKind::T => match orientation {
+                Orientation::North => [
+                    Point::new(0, 1),
+                    Point::new(1, 1),
+                    Point::new(2, 1),
+                    Point::new(1, 0),
+                ],
+                Orientation::East => [
+                    Point::new(1, 2),
+                    Point::new(0, 1),
+                    Point::new(1, 1),
+                    Point::new(1, 0),
+                ],
+                Orientation::South => [
+                    Point::new(1, 1),
+                    Point::new(0, 0),
+                    Point::new(1, 0),
+                    Point::new(2, 0),
+                ],
+                Orientation::West => [
+                    Point::new(1, 2),
+                    Point::new(1, 1),
+                    Point::new(2, 1),
+                    Point::new(1, 0),
+                ],
+            },
+            PieceKind::Z => match is_vertical {
+                false => [
+                    Point::new(0, 1),
+                    Point::new(1, 1),
+                    Point::new(1, 0),
+                    Point::new(2, 0),
+                ],
+                true => [
+                    Point::new(2, 2),
+                    Point::new(1, 1),
+                    Point::new(2, 1),
+                    Point::new(1, 0),
+                ],
             },
         }
     }
@@ -127,7 +280,7 @@ impl PieceKind {
 }
 
 #[wasm_bindgen]
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Piece {
     pub kind: PieceKind,
     /**
@@ -140,9 +293,39 @@ pub struct Piece {
 impl Piece {
     pub fn spawn(config: &Config, kind: &PieceKind) -> Piece {
         Piece {
-            kind: kind.clone(),
+            kind: *kind,
             position: kind.get_spawn_point(config),
-            orientation: Orientation::North,
+            orientation: config.spawn.orientation,
+        }
+    }
+
+    /// Spawn a piece onto `board`, or `None` if the piece tops out.
+    pub fn spawn_on_board(config: &Config, kind: &PieceKind, board: &Board) -> Option<Piece> {
+        Piece::spawn(config, kind).fitted_on_board(config, board)
+    }
+
+    /// The spawning piece if it fits on `board`, or moved up until it fits if pieces spawn above
+    /// the stack. `None` if the piece tops out.
+    pub fn fitted_on_board(&self, config: &Config, board: &Board) -> Option<Piece> {
+        let mut piece = *self;
+        if board.can_fit_mask(&piece.get_mask(config)) {
+            return Some(piece);
+        }
+        if !config.spawn.above_stack {
+            return None;
+        }
+        loop {
+            piece.position.y += 1;
+            let is_above_board = piece
+                .get_points(config)
+                .iter()
+                .any(|point| point.y >= Board::HEIGHT);
+            if is_above_board {
+                return None;
+            }
+            if board.can_fit_mask(&piece.get_mask(config)) {
+                return Some(piece);
+            }
         }
     }
 }
@@ -157,12 +340,68 @@ struct PieceOffsetBox {
 
 impl Piece {
     pub fn get_points(&self, config: &Config) -> [Point; 4] {
-        let mut unoriented_offset_box = self.kind.get_unoriented_offset_box(config);
-        orient_offset_box(&mut unoriented_offset_box, &self.orientation);
-        unoriented_offset_box
-            .offsets
+        self.kind
+            .get_oriented_offsets(config, &self.orientation)
             .map(|offset| offset + self.position)
     }
+
+    pub fn get_mask(&self, config: &Config) -> PieceMask {
+        // Every SRS variant shares the same piece orientations, and only differs in its kicks.
+        static SRS_MASKS: OnceLock<[[[u16; 4]; 4]; 7]> = OnceLock::new();
+        static ARS_MASKS: OnceLock<[[[u16; 4]; 4]; 7]> = OnceLock::new();
+        let masks = match config.kick {
+            Kick::SRS | Kick::SRSPlus | Kick::SRSX | Kick::NoKicks | Kick::Custom(_) => {
+                SRS_MASKS.get_or_init(|| get_piece_kind_masks(config))
+            }
+            Kick::ARS => ARS_MASKS.get_or_init(|| get_piece_kind_masks(config)),
+        };
+        PieceMask {
+            lines: masks[self.kind as usize][self.orientation as usize],
+            position: self.position,
+        }
+    }
+}
+
+/**
+The cells of a piece as one bitfield per line of its bounding box, ordered from bottom to top. Bit
+`x` of a line is set if column `x` of the bounding box is filled.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PieceMask {
+    pub lines: [u16; 4],
+
+    /// The bottom-left-most corner of the bounding box.
+    pub position: Point,
+}
+
+impl PieceMask {
+    pub fn moved(&self, offset: Point) -> PieceMask {
+        PieceMask {
+            position: self.position + offset,
+            ..*self
+        }
+    }
+}
+
+/// The line masks of each piece kind in each orientation, indexed by the `PieceKind` and then
+/// the `Orientation` discriminants.
+fn get_piece_kind_masks(config: &Config) -> [[[u16; 4]; 4]; 7] {
+    let orientations = [
+        Orientation::North,
+        Orientation::South,
+        Orientation::East,
+        Orientation::West,
+    ];
+    let mut masks = [[[0; 4]; 4]; 7];
+    for kind in PIECE_KINDS {
+        for orientation in orientations {
+            let lines = &mut masks[kind as usize][orientation as usize];
+            for offset in kind.get_oriented_offsets(config, &orientation) {
+                lines[offset.y as usize] |= 0b1 << offset.x;
+            }
+        }
+    }
+    masks
 }
 
 fn orient_offset_box(unoriented_offset_box: &mut PieceOffsetBox, orientation: &Orientation) {
@@ -216,6 +455,143 @@ mod tests {
         }
     }
 
+    mod get_spawn_point {
+        use crate::config::{Kick, Spawn};
+
+        use super::*;
+
+        #[test]
+        fn guideline_spawn_points() {
+            for kind in PIECE_KINDS {
+                let expected_point = match kind {
+                    PieceKind::I => Point::new(3, 18),
+                    _ => Point::new(3, 19),
+                };
+                assert_eq!(kind.get_spawn_point(&CONFIG), expected_point);
+            }
+        }
+
+        #[test]
+        fn rounds_column_right() {
+            let config = Config {
+                spawn: Spawn {
+                    column: SpawnColumn::Right,
+                    ..Spawn::default()
+                },
+                ..Config::default()
+            };
+            assert_eq!(PieceKind::T.get_spawn_point(&config), Point::new(4, 19));
+            // Even width pieces are centred either way.
+            assert_eq!(PieceKind::I.get_spawn_point(&config), Point::new(3, 18));
+            assert_eq!(PieceKind::O.get_spawn_point(&config), Point::new(3, 19));
+        }
+
+        #[test]
+        fn row_relative_to_visible_field() {
+            let config = Config {
+                spawn: Spawn {
+                    row: -2,
+                    ..Spawn::default()
+                },
+                ..Config::default()
+            };
+            let points = Piece::spawn(&config, &PieceKind::J).get_points(&config);
+            let lowest_y = points.iter().map(|point| point.y).min();
+            assert_eq!(lowest_y, Some(18));
+        }
+
+        #[test]
+        fn spawns_in_orientation() {
+            let config = Config {
+                spawn: Spawn {
+                    orientation: Orientation::East,
+                    ..Spawn::default()
+                },
+                ..Config::default()
+            };
+            let piece = Piece::spawn(&config, &PieceKind::I);
+            assert_eq!(piece.orientation, Orientation::East);
+            let points = piece.get_points(&config);
+            for point in points {
+                assert_eq!(point.x, 4);
+                assert!((20..24).contains(&point.y));
+            }
+        }
+
+        #[test]
+        fn ars_pieces_spawn_in_buffer_lines() {
+            let config = Config {
+                kick: Kick::ARS,
+                ..Config::default()
+            };
+            for kind in PIECE_KINDS {
+                let points = Piece::spawn(&config, &kind).get_points(&config);
+                for point in points {
+                    assert!(point.y == 20 || point.y == 21);
+                }
+            }
+        }
+
+        #[test]
+        fn ars_t_piece_spawns_flat_side_up() {
+            let config = Config {
+                kick: Kick::ARS,
+                ..Config::default()
+            };
+            let points = Piece::spawn(&config, &PieceKind::T).get_points(&config);
+            assert!(points.contains(&Point::new(3, 21)));
+            assert!(points.contains(&Point::new(4, 21)));
+            assert!(points.contains(&Point::new(5, 21)));
+            assert!(points.contains(&Point::new(4, 20)));
+        }
+    }
+
+    mod spawn_on_board {
+        use crate::config::Spawn;
+
+        use super::*;
+
+        fn board_with_tall_stack() -> Board {
+            let mut board = Board::empty_board();
+            for y in 0..21 {
+                board.fill(&Point::new(4, y));
+            }
+            board
+        }
+
+        #[test]
+        fn tops_out_on_stack() {
+            let piece = Piece::spawn_on_board(&CONFIG, &PieceKind::T, &board_with_tall_stack());
+            assert_eq!(piece, None);
+        }
+
+        #[test]
+        fn spawns_above_stack() {
+            let config = Config {
+                spawn: Spawn {
+                    above_stack: true,
+                    ..Spawn::default()
+                },
+                ..Config::default()
+            };
+            let piece = Piece::spawn_on_board(&config, &PieceKind::T, &board_with_tall_stack());
+            assert_eq!(piece.map(|piece| piece.position), Some(Point::new(3, 20)));
+        }
+
+        #[test]
+        fn tops_out_above_board() {
+            let config = Config {
+                spawn: Spawn {
+                    above_stack: true,
+                    ..Spawn::default()
+                },
+                ..Config::default()
+            };
+            let piece = Piece::spawn_on_board(&config, &PieceKind::T, &Board::filled_board());
+            assert_eq!(piece, None);
+        }
+    }
+
     mod orient_offsets {
         use super::*;
 
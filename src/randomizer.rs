@@ -0,0 +1,349 @@
+use crate::piece::{PieceKind, PIECE_KINDS};
+use crate::utils::piece_kind_set::PieceKindSet;
+
+/// The piece kinds dealt so far that a randomizer needs to predict the next piece kind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PieceHistory {
+    /// The number of each piece kind dealt from the current bag.
+    pub seen_piece_kind_in_bag: PieceKindSet<u8>,
+
+    /// The most recently dealt piece kinds, with the most recent first.
+    pub recent_piece_kinds: [Option<PieceKind>; 4],
+}
+
+impl PieceHistory {
+    pub fn initial() -> PieceHistory {
+        PieceHistory {
+            seen_piece_kind_in_bag: PieceKindSet::new_with_value(0),
+            recent_piece_kinds: [None; 4],
+        }
+    }
+}
+
+/// A randomizer deals piece kinds based on the piece kinds it has dealt before.
+pub trait PieceRandomizer {
+    /// The probability of each piece kind being dealt next.
+    fn next_piece_probabilities(&self, history: &PieceHistory) -> PieceKindSet<f32>;
+
+    /// The history after `kind` is dealt.
+    fn with_seen_piece_kind(&self, history: &PieceHistory, kind: &PieceKind) -> PieceHistory;
+}
+
+/// Every piece kind is equally likely to be dealt.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Random;
+
+impl PieceRandomizer for Random {
+    fn next_piece_probabilities(&self, _history: &PieceHistory) -> PieceKindSet<f32> {
+        PieceKindSet::new_with_value(1.0 / PIECE_KINDS.len() as f32)
+    }
+
+    fn with_seen_piece_kind(&self, history: &PieceHistory, _kind: &PieceKind) -> PieceHistory {
+        *history
+    }
+}
+
+/// Pieces are dealt from a bag which holds `copies` of each piece kind in a random order. Once
+/// every piece in the bag has been seen, the bag is refilled.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bag {
+    pub copies: u8,
+}
+
+impl PieceRandomizer for Bag {
+    fn next_piece_probabilities(&self, history: &PieceHistory) -> PieceKindSet<f32> {
+        let seen = &history.seen_piece_kind_in_bag;
+        let remaining_count: u8 = PIECE_KINDS
+            .iter()
+            .map(|kind| self.copies.saturating_sub(seen.get(kind)))
+            .sum();
+
+        let mut probabilities = PieceKindSet::new_with_value(0.0);
+        if remaining_count == 0 {
+            return probabilities;
+        }
+        for kind in PIECE_KINDS.iter() {
+            let remaining = self.copies.saturating_sub(seen.get(kind));
+            probabilities.set(kind, remaining as f32 / remaining_count as f32);
+        }
+        probabilities
+    }
+
+    fn with_seen_piece_kind(&self, history: &PieceHistory, kind: &PieceKind) -> PieceHistory {
+        // Seeing more copies of a piece kind than the bag holds means the previous bag has ended.
+        let mut next_seen = if history.seen_piece_kind_in_bag.get(kind) >= self.copies {
+            PieceKindSet::new_with_value(0)
+        } else {
+            history.seen_piece_kind_in_bag
+        };
+        next_seen.set(kind, next_seen.get(kind) + 1);
+        if PIECE_KINDS
+            .iter()
+            .all(|kind| next_seen.get(kind) >= self.copies)
+        {
+            next_seen = PieceKindSet::new_with_value(0);
+        }
+        PieceHistory {
+            seen_piece_kind_in_bag: next_seen,
+            ..*history
+        }
+    }
+}
+
+/// Piece kinds are rolled at random, but a roll which matches a recently dealt piece kind is
+/// rerolled up to `rolls` times in total, as in the Tetris The Grand Master series.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct History {
+    pub rolls: u8,
+}
+
+impl PieceRandomizer for History {
+    fn next_piece_probabilities(&self, history: &PieceHistory) -> PieceKindSet<f32> {
+        let mut is_recent = PieceKindSet::new_with_value(false);
+        for kind in history.recent_piece_kinds.iter().flatten() {
+            is_recent.set(kind, true);
+        }
+        let recent_count = PIECE_KINDS
+            .iter()
+            .filter(|kind| is_recent.get(kind))
+            .count();
+
+        let roll_probability = 1.0 / PIECE_KINDS.len() as f32;
+        let reroll_probability = recent_count as f32 * roll_probability;
+
+        // A recent piece kind is only dealt if every roll matches a recent piece kind, while any
+        // other piece kind is dealt as soon as it is rolled.
+        let recent_probability = reroll_probability.powi(self.rolls as i32 - 1) * roll_probability;
+        let other_probability = (0..self.rolls)
+            .map(|roll| reroll_probability.powi(roll as i32) * roll_probability)
+            .sum::<f32>();
+
+        let mut probabilities = PieceKindSet::new_with_value(0.0);
+        for kind in PIECE_KINDS.iter() {
+            let probability = if is_recent.get(kind) {
+                recent_probability
+            } else {
+                other_probability
+            };
+            probabilities.set(kind, probability);
+        }
+        probabilities
+    }
+
+    fn with_seen_piece_kind(&self, history: &PieceHistory, kind: &PieceKind) -> PieceHistory {
+        let mut recent_piece_kinds = [None; 4];
+        recent_piece_kinds[0] = Some(*kind);
+        recent_piece_kinds[1..].copy_from_slice(&history.recent_piece_kinds[..3]);
+        PieceHistory {
+            recent_piece_kinds,
+            ..*history
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::history_with_seen;
+
+    use super::*;
+
+    fn assert_sums_to_one(probabilities: &PieceKindSet<f32>) {
+        let total: f32 = PIECE_KINDS.iter().map(|kind| probabilities.get(kind)).sum();
+        assert!(
+            (total - 1.0).abs() < 1e-6,
+            "Expected probabilities to sum to 1, got {}",
+            total
+        );
+    }
+
+    mod random {
+        use super::*;
+
+        #[test]
+        fn uniform_regardless_of_history() {
+            let history = Random.with_seen_piece_kind(&PieceHistory::initial(), &PieceKind::I);
+
+            let probabilities = Random.next_piece_probabilities(&history);
+
+            for kind in PIECE_KINDS.iter() {
+                assert_eq!(probabilities.get(kind), 1.0 / 7.0);
+            }
+        }
+    }
+
+    mod seven_bag {
+        use super::*;
+
+        const SEVEN_BAG: Bag = Bag { copies: 1 };
+
+        #[test]
+        fn marks_piece_kind_seen() {
+            let history = history_with_seen(&[PieceKind::I]);
+
+            let next_history = SEVEN_BAG.with_seen_piece_kind(&history, &PieceKind::T);
+
+            assert_eq!(
+                next_history,
+                history_with_seen(&[PieceKind::I, PieceKind::T])
+            );
+        }
+
+        #[test]
+        fn refills_bag_after_every_piece_kind_seen() {
+            let history = history_with_seen(&[
+                PieceKind::I,
+                PieceKind::J,
+                PieceKind::L,
+                PieceKind::O,
+                PieceKind::S,
+                PieceKind::T,
+            ]);
+
+            let next_history = SEVEN_BAG.with_seen_piece_kind(&history, &PieceKind::Z);
+
+            assert_eq!(next_history, history_with_seen(&[]));
+        }
+
+        #[test]
+        fn starts_new_bag_if_piece_kind_already_seen() {
+            let history = history_with_seen(&[PieceKind::I, PieceKind::T]);
+
+            let next_history = SEVEN_BAG.with_seen_piece_kind(&history, &PieceKind::I);
+
+            assert_eq!(next_history, history_with_seen(&[PieceKind::I]));
+        }
+
+        #[test]
+        fn uniform_for_new_bag() {
+            let probabilities = SEVEN_BAG.next_piece_probabilities(&history_with_seen(&[]));
+
+            for kind in PIECE_KINDS.iter() {
+                assert_eq!(probabilities.get(kind), 1.0 / 7.0);
+            }
+        }
+
+        #[test]
+        fn only_unseen_piece_kinds() {
+            let probabilities = SEVEN_BAG.next_piece_probabilities(&history_with_seen(&[
+                PieceKind::I,
+                PieceKind::O,
+                PieceKind::T,
+            ]));
+
+            for kind in [PieceKind::I, PieceKind::O, PieceKind::T] {
+                assert_eq!(probabilities.get(&kind), 0.0);
+            }
+            for kind in [PieceKind::J, PieceKind::L, PieceKind::S, PieceKind::Z] {
+                assert_eq!(probabilities.get(&kind), 0.25);
+            }
+        }
+
+        #[test]
+        fn certain_for_last_piece_kind_in_bag() {
+            let probabilities = SEVEN_BAG.next_piece_probabilities(&history_with_seen(&[
+                PieceKind::I,
+                PieceKind::J,
+                PieceKind::L,
+                PieceKind::S,
+                PieceKind::T,
+                PieceKind::Z,
+            ]));
+
+            assert_eq!(probabilities.get(&PieceKind::O), 1.0);
+        }
+    }
+
+    mod fourteen_bag {
+        use super::*;
+
+        const FOURTEEN_BAG: Bag = Bag { copies: 2 };
+
+        #[test]
+        fn allows_second_copy_of_piece_kind() {
+            let history = history_with_seen(&[PieceKind::I]);
+
+            let next_history = FOURTEEN_BAG.with_seen_piece_kind(&history, &PieceKind::I);
+
+            assert_eq!(
+                next_history,
+                history_with_seen(&[PieceKind::I, PieceKind::I])
+            );
+        }
+
+        #[test]
+        fn weights_by_remaining_copies() {
+            let probabilities = FOURTEEN_BAG.next_piece_probabilities(&history_with_seen(&[
+                PieceKind::I,
+                PieceKind::I,
+                PieceKind::O,
+            ]));
+
+            assert_sums_to_one(&probabilities);
+            assert_eq!(probabilities.get(&PieceKind::I), 0.0);
+            assert_eq!(probabilities.get(&PieceKind::O), 1.0 / 11.0);
+            assert_eq!(probabilities.get(&PieceKind::T), 2.0 / 11.0);
+        }
+    }
+
+    mod history {
+        use super::*;
+
+        const TGM: History = History { rolls: 4 };
+
+        #[test]
+        fn pushes_recent_piece_kinds() {
+            let history = [
+                PieceKind::I,
+                PieceKind::J,
+                PieceKind::L,
+                PieceKind::O,
+                PieceKind::S,
+            ]
+            .iter()
+            .fold(PieceHistory::initial(), |history, kind| {
+                TGM.with_seen_piece_kind(&history, kind)
+            });
+
+            assert_eq!(
+                history.recent_piece_kinds,
+                [
+                    Some(PieceKind::S),
+                    Some(PieceKind::O),
+                    Some(PieceKind::L),
+                    Some(PieceKind::J),
+                ]
+            );
+        }
+
+        #[test]
+        fn uniform_without_history() {
+            let probabilities = TGM.next_piece_probabilities(&PieceHistory::initial());
+
+            assert_sums_to_one(&probabilities);
+            for kind in PIECE_KINDS.iter() {
+                assert!((probabilities.get(kind) - 1.0 / 7.0).abs() < 1e-6);
+            }
+        }
+
+        #[test]
+        fn recent_piece_kinds_less_likely() {
+            let history = PieceHistory {
+                recent_piece_kinds: [
+                    Some(PieceKind::S),
+                    Some(PieceKind::Z),
+                    Some(PieceKind::S),
+                    Some(PieceKind::Z),
+                ],
+                ..PieceHistory::initial()
+            };
+
+            let probabilities = TGM.next_piece_probabilities(&history);
+
+            assert_sums_to_one(&probabilities);
+            let recent_probability = (2.0f32 / 7.0).powi(3) / 7.0;
+            assert!((probabilities.get(&PieceKind::S) - recent_probability).abs() < 1e-6);
+            assert!((probabilities.get(&PieceKind::Z) - recent_probability).abs() < 1e-6);
+            assert!(probabilities.get(&PieceKind::T) > probabilities.get(&PieceKind::S));
+        }
+    }
+}
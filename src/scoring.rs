@@ -0,0 +1,208 @@
+use crate::game::{PlaceOutcome, Spin};
+
+/// Points, combo and back-to-back state under guideline scoring.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Score {
+    pub points: u32,
+
+    /// Multiplies the points of every placement.
+    pub level: u32,
+
+    /// The number of placements in a row which cleared lines.
+    pub combo: u32,
+
+    /// The number of line clears in a row which were tetrises or spins. Placements which clear no
+    /// lines do not break the chain.
+    pub back_to_back: u32,
+}
+
+impl Score {
+    pub const fn initial() -> Score {
+        Score {
+            points: 0,
+            level: 1,
+            combo: 0,
+            back_to_back: 0,
+        }
+    }
+
+    /**
+    The score after a placement with `outcome`.
+
+    Tetrises and line clears with a spin are difficult, and score half as many points again when
+    they continue a back-to-back chain. Every placement in a combo after the first scores 50 points
+    for each placement before it. All-spins score as T-spin minis.
+    */
+    pub fn with_outcome(&self, outcome: &PlaceOutcome) -> Score {
+        let lines_cleared = outcome.lines_cleared();
+        if lines_cleared == 0 {
+            return Score {
+                points: self.points + get_clear_points(0, outcome.spin) * self.level,
+                combo: 0,
+                ..*self
+            };
+        }
+
+        let is_difficult = lines_cleared >= 4 || outcome.spin.is_some();
+        let is_back_to_back = is_difficult && self.back_to_back > 0;
+
+        let mut clear_points = get_clear_points(lines_cleared, outcome.spin);
+        if is_back_to_back {
+            clear_points = clear_points * 3 / 2;
+        }
+        let combo_points = 50 * self.combo;
+        let perfect_clear_points = if outcome.is_perfect_clear {
+            get_perfect_clear_points(lines_cleared, is_back_to_back)
+        } else {
+            0
+        };
+
+        Score {
+            points: self.points + (clear_points + combo_points + perfect_clear_points) * self.level,
+            combo: self.combo + 1,
+            back_to_back: if is_difficult {
+                self.back_to_back + 1
+            } else {
+                0
+            },
+            ..*self
+        }
+    }
+}
+
+fn get_clear_points(lines_cleared: usize, spin: Option<Spin>) -> u32 {
+    match (spin, lines_cleared) {
+        (None, 0) => 0,
+        (None, 1) => 100,
+        (None, 2) => 300,
+        (None, 3) => 500,
+        (None, _) => 800,
+        (Some(Spin::TSpin), 0) => 400,
+        (Some(Spin::TSpin), 1) => 800,
+        (Some(Spin::TSpin), 2) => 1200,
+        (Some(Spin::TSpin), _) => 1600,
+        (Some(Spin::TSpinMini | Spin::AllSpin), 0) => 100,
+        (Some(Spin::TSpinMini | Spin::AllSpin), 1) => 200,
+        (Some(Spin::TSpinMini | Spin::AllSpin), _) => 400,
+    }
+}
+
+fn get_perfect_clear_points(lines_cleared: usize, is_back_to_back: bool) -> u32 {
+    match lines_cleared {
+        1 => 800,
+        2 => 1200,
+        3 => 1800,
+        _ if is_back_to_back => 3200,
+        _ => 2000,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outcome(lines_cleared: isize, spin: Option<Spin>) -> PlaceOutcome {
+        PlaceOutcome {
+            cleared_rows: (0..lines_cleared).collect(),
+            is_perfect_clear: false,
+            spin,
+        }
+    }
+
+    mod with_outcome {
+        use super::*;
+
+        #[test]
+        fn line_clears() {
+            for (lines_cleared, points) in [(0, 0), (1, 100), (2, 300), (3, 500), (4, 800)] {
+                let score = Score::initial().with_outcome(&outcome(lines_cleared, None));
+
+                assert_eq!(score.points, points);
+            }
+        }
+
+        #[test]
+        fn spins() {
+            for (lines_cleared, spin, points) in [
+                (0, Spin::TSpin, 400),
+                (2, Spin::TSpin, 1200),
+                (3, Spin::TSpin, 1600),
+                (0, Spin::TSpinMini, 100),
+                (1, Spin::TSpinMini, 200),
+                (1, Spin::AllSpin, 200),
+            ] {
+                let score = Score::initial().with_outcome(&outcome(lines_cleared, Some(spin)));
+
+                assert_eq!(score.points, points);
+            }
+        }
+
+        #[test]
+        fn multiplied_by_level() {
+            let score = Score {
+                level: 3,
+                ..Score::initial()
+            }
+            .with_outcome(&outcome(4, None));
+
+            assert_eq!(score.points, 2400);
+        }
+
+        #[test]
+        fn combo() {
+            let score = Score::initial()
+                .with_outcome(&outcome(1, None))
+                .with_outcome(&outcome(1, None))
+                .with_outcome(&outcome(1, None));
+
+            assert_eq!(score.combo, 3);
+            assert_eq!(score.points, 100 + 150 + 200);
+
+            let score = score.with_outcome(&outcome(0, None));
+
+            assert_eq!(score.combo, 0);
+        }
+
+        #[test]
+        fn back_to_back() {
+            let score = Score::initial()
+                .with_outcome(&outcome(4, None))
+                .with_outcome(&outcome(0, None))
+                .with_outcome(&outcome(2, Some(Spin::TSpin)));
+
+            assert_eq!(score.back_to_back, 2);
+            assert_eq!(score.points, 800 + 1800);
+        }
+
+        #[test]
+        fn easy_line_clear_breaks_back_to_back() {
+            let score = Score::initial()
+                .with_outcome(&outcome(4, None))
+                .with_outcome(&outcome(1, None));
+
+            assert_eq!(score.back_to_back, 0);
+        }
+
+        #[test]
+        fn perfect_clear() {
+            let score = Score::initial().with_outcome(&PlaceOutcome {
+                is_perfect_clear: true,
+                ..outcome(2, None)
+            });
+
+            assert_eq!(score.points, 300 + 1200);
+        }
+
+        #[test]
+        fn back_to_back_tetris_perfect_clear() {
+            let score = Score::initial()
+                .with_outcome(&outcome(4, None))
+                .with_outcome(&PlaceOutcome {
+                    is_perfect_clear: true,
+                    ..outcome(4, None)
+                });
+
+            assert_eq!(score.points, 800 + 1200 + 50 + 3200);
+        }
+    }
+}
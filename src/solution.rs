@@ -0,0 +1,186 @@
+use crate::attack::AttackTable;
+use crate::board::Board;
+use crate::game::{Move, PlaceOutcome};
+use crate::piece::Piece;
+use crate::scoring::Score;
+use crate::utils::rotation::Rotation;
+
+/// A single step of a perfect clear solution.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Placement {
+    /// The kind, position, and orientation of the piece when it is placed.
+    pub piece: Piece,
+
+    /// The rotation held as the piece spawned, before any moves are made.
+    pub initial_rotation: Option<Rotation>,
+
+    /// The fewest moves needed to reach `piece` from where it spawns.
+    pub moves: Vec<Move>,
+
+    /// Whether the piece was switched in from hold before it was placed.
+    pub is_hold_used: bool,
+
+    /// The board after the piece is placed and any filled lines are cleared.
+    pub board: Board,
+
+    pub outcome: PlaceOutcome,
+
+    /// The probability of receiving the piece at this step.
+    pub probability: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Solution {
+    /// Placements in the order they are made.
+    pub placements: Vec<Placement>,
+}
+
+impl Solution {
+    pub fn new(placements: Vec<Placement>) -> Solution {
+        Solution { placements }
+    }
+
+    /// The probability of receiving every piece in the solution.
+    pub fn probability(&self) -> f32 {
+        self.placements
+            .iter()
+            .map(|placement| placement.probability)
+            .product()
+    }
+
+    /// The board after the final placement.
+    pub fn board(&self) -> Option<Board> {
+        self.placements.last().map(|placement| placement.board)
+    }
+
+    /// The score after making every placement, starting from `score`.
+    pub fn score(&self, score: &Score) -> Score {
+        self.placements.iter().fold(*score, |score, placement| {
+            score.with_outcome(&placement.outcome)
+        })
+    }
+
+    /// The total lines sent by every placement under `attack_table`, starting from `score`.
+    pub fn attack(&self, attack_table: &AttackTable, score: &Score) -> u32 {
+        self.placements
+            .iter()
+            .fold((0, *score), |(attack, score), placement| {
+                (
+                    attack + attack_table.get_attack(&placement.outcome, &score),
+                    score.with_outcome(&placement.outcome),
+                )
+            })
+            .0
+    }
+}
+
+/// The chance of a perfect clear over every sequence of pieces which could still be dealt.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PerfectClearPercent {
+    /// The probability of being dealt a sequence of pieces which allows a perfect clear.
+    pub probability: f32,
+
+    /// Each possible first placement, paired with the probability of being dealt a sequence of
+    /// pieces which allows a perfect clear after that placement.
+    pub by_first_placement: Vec<(Placement, f32)>,
+}
+
+impl PerfectClearPercent {
+    /// The first placement with the best chance of a perfect clear.
+    pub fn best_first_placement(&self) -> Option<&(Placement, f32)> {
+        self.by_first_placement
+            .iter()
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Config;
+    use crate::piece::PieceKind;
+
+    use super::*;
+
+    const CONFIG: Config = Config::default();
+
+    fn placement(kind: PieceKind, probability: f32) -> Placement {
+        Placement {
+            piece: Piece::spawn(&CONFIG, &kind),
+            initial_rotation: None,
+            moves: vec![],
+            is_hold_used: false,
+            board: Board::empty_board(),
+            outcome: PlaceOutcome {
+                cleared_rows: vec![],
+                is_perfect_clear: false,
+                spin: None,
+            },
+            probability,
+        }
+    }
+
+    mod probability {
+        use super::*;
+
+        #[test]
+        fn multiplies_step_probabilities() {
+            let solution = Solution::new(vec![
+                placement(PieceKind::I, 1.0),
+                placement(PieceKind::O, 0.5),
+                placement(PieceKind::T, 0.25),
+            ]);
+
+            assert_eq!(solution.probability(), 0.125);
+        }
+
+        #[test]
+        fn certain_if_no_placements() {
+            let solution = Solution::new(vec![]);
+
+            assert_eq!(solution.probability(), 1.0);
+        }
+    }
+
+    mod score {
+        use super::*;
+
+        #[test]
+        fn scores_each_outcome_in_order() {
+            let clear = |lines_cleared: isize, is_perfect_clear: bool| Placement {
+                outcome: PlaceOutcome {
+                    cleared_rows: (0..lines_cleared).collect(),
+                    is_perfect_clear,
+                    spin: None,
+                },
+                ..placement(PieceKind::I, 1.0)
+            };
+            let solution = Solution::new(vec![clear(0, false), clear(1, false), clear(3, true)]);
+
+            let score = solution.score(&Score::initial());
+
+            assert_eq!(score.points, 100 + 500 + 50 + 1800);
+            assert_eq!(score.combo, 2);
+        }
+    }
+
+    mod attack {
+        use super::*;
+
+        #[test]
+        fn sends_lines_with_combo_and_back_to_back() {
+            let clear = |lines_cleared: isize| Placement {
+                outcome: PlaceOutcome {
+                    cleared_rows: (0..lines_cleared).collect(),
+                    is_perfect_clear: false,
+                    spin: None,
+                },
+                ..placement(PieceKind::I, 1.0)
+            };
+            let solution = Solution::new(vec![clear(4), clear(4), clear(2)]);
+
+            let attack = solution.attack(&AttackTable::TetrisFriends, &Score::initial());
+
+            assert_eq!(attack, 4 + (4 + 1 + 1) + (1 + 1));
+        }
+    }
+}
@@ -1,28 +1,34 @@
 use crate::board::Board;
 use crate::config::Config;
-use crate::game::{Action as GameAction, Game};
-use crate::piece::{Piece, PieceKind, PIECE_KINDS};
-use crate::state::{Action, State};
-use crate::utils::point::Point;
-use crate::utils::rotation::Orientation;
+use crate::game::{Action as GameAction, Game, InitialActions, Move};
+use crate::move_generator::generate_lock_positions;
+use crate::piece::{PieceKind, PIECE_KINDS};
+use crate::randomizer::{PieceHistory, PieceRandomizer};
+use crate::scoring::Score;
+use crate::solution::{PerfectClearPercent, Placement, Solution};
+use crate::state::{Action, State, StateKey};
+use crate::utils::rotation::Rotation;
 use crate::utils::weight_indexed_graph::WeightIndexedGraph;
-use petgraph::algo::all_simple_paths;
 use petgraph::graph::NodeIndex;
 use petgraph::visit::EdgeRef;
-use std::collections::HashMap;
-use std::hash::{Hash, Hasher};
+use std::collections::{HashMap, HashSet};
+use std::iter;
 use wasm_bindgen::prelude::*;
 
 #[wasm_bindgen]
 pub struct Solver {
+    config: Config,
     current_state: State,
+    transposition_table: TranspositionTable,
 }
 
 #[wasm_bindgen]
 impl Solver {
     pub fn new() -> Solver {
         Solver {
+            config: Config::default(),
             current_state: State::initial(),
+            transposition_table: TranspositionTable::new(),
         }
     }
 
@@ -31,77 +37,185 @@ impl Solver {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
-struct GraphNode {
-    board: Board,
-    moves_remaining: u8,
-    is_valid: bool,
+impl Default for Solver {
+    fn default() -> Self {
+        Solver::new()
+    }
+}
+
+impl Solver {
+    pub fn with_config(config: Config) -> Solver {
+        Solver {
+            config,
+            ..Solver::new()
+        }
+    }
+
+    /// Results found under one config do not hold under another, so the transposition table is
+    /// cleared whenever the config changes.
+    pub fn update_config(&mut self, config: Config) {
+        if config != self.config {
+            self.config = config;
+            self.transposition_table.clear();
+        }
+    }
+
+    pub fn update_state(&mut self, state: State) {
+        self.current_state = state;
+    }
+
+    /// Find the best move for the current state, reusing the probabilities found by earlier calls.
+    pub fn best_move(&mut self) -> Option<(Placement, f32)> {
+        best_move_with_table(
+            &self.config,
+            &self.current_state,
+            &mut self.transposition_table,
+        )
+    }
+}
+
+/// Memoises the probability of a perfect clear from each state, so that equivalent states
+/// reached through different branches are only solved once.
+#[derive(Debug, Clone)]
+pub struct TranspositionTable {
+    probabilities: HashMap<StateKey, f32>,
+}
+
+impl TranspositionTable {
+    pub fn new() -> TranspositionTable {
+        TranspositionTable {
+            probabilities: HashMap::new(),
+        }
+    }
+
+    pub fn get(&self, key: &StateKey) -> Option<f32> {
+        self.probabilities.get(key).copied()
+    }
+
+    pub fn insert(&mut self, key: StateKey, probability: f32) {
+        self.probabilities.insert(key, probability);
+    }
+
+    pub fn clear(&mut self) {
+        self.probabilities.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.probabilities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.probabilities.is_empty()
+    }
 }
 
-impl Hash for GraphNode {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        self.board.hash(state);
-        self.moves_remaining.hash(state);
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        TranspositionTable::new()
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+struct GraphNode {
+    key: StateKey,
+    is_valid: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 struct GraphEdge {
-    piece_kind: PieceKind,
+    placement: Placement,
 }
 
-pub fn get_perfect_clear_paths(
-    config: &Config,
-    state: &State,
-) -> Vec<Vec<(Board, PieceKind, f32)>> {
+pub fn get_perfect_clear_paths(config: &Config, state: &State) -> Vec<Solution> {
     let mut node_graph = WeightIndexedGraph::new();
-    let board_too_high = !state.game.board.is_line_empty(4);
+    let board_too_high = !state.game.board.is_line_empty(config.perfect_clear_height);
     let can_perfect_clear = state.game.board.can_perfect_clear();
     let out_of_moves = state.moves_remaining == 0 && !can_perfect_clear;
-    let is_valid = !board_too_high && !out_of_moves;
+    let is_dead =
+        !can_perfect_clear && is_dead_board(config, &state.game.board, state.moves_remaining);
+    let is_valid = !board_too_high && !out_of_moves && !is_dead;
     let node_idx = node_graph.update_node(GraphNode {
-        board: state.game.board,
-        moves_remaining: state.moves_remaining,
+        key: state.key(),
         is_valid,
     });
-    generate_next_states(config, state, node_idx, &mut node_graph);
-    get_perfect_clear_paths_from_graph(&node_graph)
+    if is_valid && !can_perfect_clear {
+        generate_next_states(config, state, node_idx, &mut node_graph);
+    }
+    get_perfect_clear_paths_from_graph(&node_graph, node_idx)
 }
 
 fn get_perfect_clear_paths_from_graph(
     node_graph: &WeightIndexedGraph<GraphNode, GraphEdge>,
-) -> Vec<Vec<(Board, PieceKind, f32)>> {
-    // TODO Re-implement this.
-    vec![]
-    // let Some(empty_idx) = node_graph.get_node_index(Board::empty_board()) else {
-    //     return vec![];
-    // };
-    // let graph = &node_graph.graph;
-
-    // let paths = Board::PC_BOARDS
-    //     .iter()
-    //     .filter_map(|&board| node_graph.get_node_index(board))
-    //     .map(|pc_idx| {
-    //         all_simple_paths::<Vec<_>, _>(graph, empty_idx, pc_idx, 4, None).collect::<Vec<_>>()
-    //     })
-    //     .reduce(|total, prev| [total, prev].concat())
-    //     .unwrap_or(vec![]);
-
-    // paths
-    //     .iter()
-    //     .map(|indices| {
-    //         indices
-    //             .windows(2)
-    //             .map(|window| {
-    //                 let from = window[0];
-    //                 let to = window[1];
-    //                 let from_board = graph[from];
-    //                 let edge = graph.edges_connecting(from, to).next().unwrap().weight();
-    //                 (from_board, edge.piece_kind, edge.probability)
-    //             })
-    //             .collect()
-    //     })
-    //     .collect()
+    root_idx: NodeIndex,
+) -> Vec<Solution> {
+    let mut paths = vec![];
+    let mut in_progress = vec![];
+    collect_perfect_clear_paths(node_graph, root_idx, &mut in_progress, &mut paths);
+    paths
+}
+
+/// Every perfect clear path from `state` as in [`get_perfect_clear_paths`], from the most likely
+/// to the least likely. Equally likely paths are ordered from the most points scored, starting
+/// from the score of `state` if it is scored.
+pub fn get_perfect_clear_paths_by_score(config: &Config, state: &State) -> Vec<Solution> {
+    rank_perfect_clear_paths(config, state, |path, score| path.score(score).points)
+}
+
+/// Every perfect clear path from `state` as in [`get_perfect_clear_paths_by_score`], where equally
+/// likely paths are instead ordered from the most lines sent under the configured attack table.
+pub fn get_perfect_clear_paths_by_attack(config: &Config, state: &State) -> Vec<Solution> {
+    rank_perfect_clear_paths(config, state, |path, score| {
+        path.attack(&config.attack_table, score)
+    })
+}
+
+/// Order the perfect clear paths from `state` from the most likely, then from the highest
+/// `value`, which is given the score of `state` before the path.
+fn rank_perfect_clear_paths(
+    config: &Config,
+    state: &State,
+    value: impl Fn(&Solution, &Score) -> u32,
+) -> Vec<Solution> {
+    let score = state.game.score.unwrap_or(Score::initial());
+    let mut paths = get_perfect_clear_paths(config, state)
+        .into_iter()
+        .map(|path| (value(&path, &score), path))
+        .collect::<Vec<_>>();
+    paths.sort_by(|(a_value, a), (b_value, b)| {
+        b.probability()
+            .total_cmp(&a.probability())
+            .then(b_value.cmp(a_value))
+    });
+    paths.into_iter().map(|(_, path)| path).collect()
+}
+
+/// The graph is acyclic because every edge consumes a move, so a depth-first walk visits each
+/// path from `node_idx` exactly once.
+fn collect_perfect_clear_paths(
+    node_graph: &WeightIndexedGraph<GraphNode, GraphEdge>,
+    node_idx: NodeIndex,
+    in_progress: &mut Vec<Placement>,
+    paths: &mut Vec<Solution>,
+) {
+    let graph = &node_graph.graph;
+    let node = graph[node_idx];
+
+    if node.key.board.can_perfect_clear() {
+        if !in_progress.is_empty() {
+            paths.push(Solution::new(in_progress.clone()));
+        }
+        return;
+    }
+
+    if !node.is_valid {
+        return;
+    }
+
+    for edge in graph.edges(node_idx) {
+        in_progress.push(edge.weight().placement.clone());
+        collect_perfect_clear_paths(node_graph, edge.target(), in_progress, paths);
+        in_progress.pop();
+    }
 }
 
 fn generate_next_states(
@@ -111,48 +225,50 @@ fn generate_next_states(
     node_graph: &mut WeightIndexedGraph<GraphNode, GraphEdge>,
 ) {
     branch_state_for_piece(config, previous_state)
-        .iter()
-        .flat_map(|state_with_piece| {
-            branch_game_on_hold(config, &state_with_piece.game)
-                .into_iter()
-                .map(move |game_after_hold| State {
-                    game: game_after_hold,
-                    ..state_with_piece.clone()
-                })
-        })
-        .flat_map(|state_after_hold| {
-            branch_game_to_placable_pieces(config, &state_after_hold.game)
-                .into_iter()
-                .map(move |game_after_move| State {
-                    game: game_after_move,
-                    ..state_after_hold.clone()
-                })
-        })
-        .map(|state_after_move| {
-            (
-                state_after_move
-                    .reduce(config, &Action::Play(GameAction::Place))
-                    .unwrap(),
-                state_after_move.game.piece.unwrap().kind,
-            )
+        .into_iter()
+        .flat_map(|(state_with_piece, probability)| {
+            branch_state_to_placed_pieces(config, &state_with_piece, probability)
         })
-        .for_each(|(state_after_place, piece_kind)| {
-            let board_too_high = !state_after_place.game.board.is_line_empty(4);
+        .for_each(|(state_after_place, placement)| {
+            let board_too_high = !state_after_place
+                .game
+                .board
+                .is_line_empty(config.perfect_clear_height);
             let can_perfect_clear = state_after_place.game.board.can_perfect_clear();
             let out_of_moves = state_after_place.moves_remaining == 0 && !can_perfect_clear;
+            let is_dead = !can_perfect_clear
+                && is_dead_board(
+                    config,
+                    &state_after_place.game.board,
+                    state_after_place.moves_remaining,
+                );
 
-            let is_valid = !board_too_high && !out_of_moves;
+            let is_valid = !board_too_high && !out_of_moves && !is_dead;
 
             let graph_node = GraphNode {
-                board: state_after_place.game.board,
+                key: state_after_place.key(),
                 is_valid,
-                moves_remaining: state_after_place.moves_remaining,
             };
 
+            let graph_edge = GraphEdge { placement };
+
             if let Some(node_idx) = node_graph.get_node_index(graph_node) {
-                node_graph
+                // Different placements of the same piece can lock into the same board. Those with
+                // different spins are kept, since they score and attack differently.
+                let is_duplicate_edge = node_graph
                     .graph
-                    .add_edge(previous_node_idx, node_idx, GraphEdge { piece_kind });
+                    .edges_connecting(previous_node_idx, node_idx)
+                    .any(|edge| {
+                        let placement = &edge.weight().placement;
+                        placement.piece.kind == graph_edge.placement.piece.kind
+                            && placement.is_hold_used == graph_edge.placement.is_hold_used
+                            && placement.outcome.spin == graph_edge.placement.outcome.spin
+                    });
+                if !is_duplicate_edge {
+                    node_graph
+                        .graph
+                        .add_edge(previous_node_idx, node_idx, graph_edge);
+                }
                 return;
             }
 
@@ -160,11 +276,11 @@ fn generate_next_states(
                 return;
             };
 
-            if can_perfect_clear {
-                println!("found a perfect clear solution");
-            }
+            node_graph
+                .graph
+                .add_edge(previous_node_idx, node_idx, graph_edge);
 
-            if can_perfect_clear || board_too_high || out_of_moves {
+            if can_perfect_clear || !is_valid {
                 return;
             }
 
@@ -172,96 +288,559 @@ fn generate_next_states(
         });
 }
 
-const NEXT_PROBABILITY: f32 = 1.0 / 7.0;
+/// Find the chance of a perfect clear from `state` over every sequence of pieces which could be
+/// dealt after the queue, given that the whole sequence is known when placing pieces.
+pub fn get_perfect_clear_percent(config: &Config, state: &State) -> PerfectClearPercent {
+    let mut percent = PerfectClearPercent {
+        probability: 0.0,
+        by_first_placement: vec![],
+    };
 
-fn branch_state_for_piece(config: &Config, state: &State) -> Vec<State> {
-    if state.game.piece.is_some() {
-        return vec![state.clone()];
+    if state.game.board.can_perfect_clear() {
+        percent.probability = 1.0;
+        return percent;
     }
-    if let Ok(state_after_consume_queue) = state.reduce(config, &Action::ConsumeQueue) {
-        return vec![state_after_consume_queue];
+    if is_dead_board(config, &state.game.board, state.moves_remaining) {
+        return percent;
     }
-    PIECE_KINDS
+
+    // Every placement deals one new piece, and holding into an empty hold deals one more.
+    let known_kinds: Vec<PieceKind> = state
+        .game
+        .piece
         .iter()
-        .filter_map(|&kind| {
-            // Assume all next pieces are equally likely for now.
-            // TODO Calculate next probabilities.
-            let guess_probability = NEXT_PROBABILITY;
-            match state.reduce(config, &Action::WithNextPiece { kind }) {
-                Ok(state) => Some(state),
-                Err(_) => None,
+        .map(|piece| piece.kind)
+        .chain(state.game.queue.iter().flatten().copied())
+        .collect();
+    let dealt_count = state.moves_remaining as usize + usize::from(state.game.hold_kind.is_none());
+    let unknown_count = dealt_count.saturating_sub(known_kinds.len());
+    let history_after_queue = state
+        .game
+        .queue
+        .iter()
+        .flatten()
+        .fold(state.piece_history, |history, kind| {
+            config.randomizer.with_seen_piece_kind(&history, kind)
+        });
+
+    for (sequence, sequence_probability) in
+        get_piece_sequences(config, &history_after_queue, unknown_count)
+    {
+        let Some((state_with_piece, rest_sequence)) =
+            next_state_with_piece(config, state, &sequence)
+        else {
+            continue;
+        };
+        // The first piece is only guessed if nothing is known about it.
+        let first_probability = if known_kinds.is_empty() {
+            config
+                .randomizer
+                .next_piece_probabilities(&state.piece_history)
+                .get(&sequence[0])
+        } else {
+            1.0
+        };
+
+        let mut failed_states = HashSet::new();
+        let mut is_perfect_clear_found = false;
+        for (state_after_place, placement) in
+            branch_state_to_placed_pieces(config, &state_with_piece, first_probability)
+        {
+            let has_perfect_clear = has_perfect_clear(
+                config,
+                &state_after_place,
+                rest_sequence,
+                &mut failed_states,
+            );
+
+            let entry_idx = percent
+                .by_first_placement
+                .iter()
+                .position(|(first_placement, _)| {
+                    first_placement.piece == placement.piece
+                        && first_placement.is_hold_used == placement.is_hold_used
+                })
+                .unwrap_or_else(|| {
+                    percent.by_first_placement.push((placement, 0.0));
+                    percent.by_first_placement.len() - 1
+                });
+            if has_perfect_clear {
+                percent.by_first_placement[entry_idx].1 += sequence_probability;
+                is_perfect_clear_found = true;
             }
+        }
+        if is_perfect_clear_found {
+            percent.probability += sequence_probability;
+        }
+    }
+
+    percent
+}
+
+/// Find the placement of the next known piece, possibly after holding, which maximises the
+/// probability of a perfect clear when unknown pieces are only revealed as they are dealt. The
+/// placement is paired with that probability.
+///
+/// If the best move is to hold into an empty hold while nothing is queued, the piece placed after
+/// holding is not known yet. The placement is then the best one for the piece which contributes
+/// the most to the probability, and its `probability` is the chance of receiving that piece.
+///
+/// Returns `None` if the next piece is unknown or no placement can be made.
+pub fn best_move(config: &Config, state: &State) -> Option<(Placement, f32)> {
+    best_move_with_table(config, state, &mut TranspositionTable::new())
+}
+
+/// Find the best move as in [`best_move`], reusing and adding to the probabilities memoised in
+/// `table`. The table must only be shared between calls with the same config.
+pub fn best_move_with_table(
+    config: &Config,
+    state: &State,
+    table: &mut TranspositionTable,
+) -> Option<(Placement, f32)> {
+    if state.game.board.can_perfect_clear()
+        || is_dead_board(config, &state.game.board, state.moves_remaining)
+    {
+        return None;
+    }
+    if state.game.piece.is_none() && state.game.queue[0].is_none() {
+        return None;
+    }
+
+    let (state_with_piece, _) = branch_state_for_piece(config, state).into_iter().next()?;
+    let best = get_best_placement(config, &state_with_piece, table);
+
+    let mut hold_probability = 0.0;
+    let mut best_hold: Option<(Placement, f32)> = None;
+    for (state_after_hold, probability) in
+        branch_state_on_hold_into_unknown(config, &state_with_piece)
+    {
+        let Some((placement, best_probability)) =
+            get_best_placement(config, &state_after_hold, table)
+        else {
+            continue;
+        };
+        hold_probability += probability * best_probability;
+        if best_hold
+            .as_ref()
+            .is_none_or(|(_, contribution)| probability * best_probability > *contribution)
+        {
+            let placement = Placement {
+                is_hold_used: true,
+                probability,
+                ..placement
+            };
+            best_hold = Some((placement, probability * best_probability));
+        }
+    }
+
+    match (best, best_hold) {
+        (Some((placement, probability)), _) if probability >= hold_probability => {
+            Some((placement, probability))
+        }
+        (_, Some((placement, _))) => Some((placement, hold_probability)),
+        (best, None) => best,
+    }
+}
+
+/// Find the placement of the active piece of `state` which maximises the probability of a perfect
+/// clear as in [`get_expected_perfect_clear_probability`], paired with that probability.
+fn get_best_placement(
+    config: &Config,
+    state: &State,
+    table: &mut TranspositionTable,
+) -> Option<(Placement, f32)> {
+    branch_state_to_placed_pieces(config, state, 1.0)
+        .into_iter()
+        .map(|(state_after_place, placement)| {
+            let probability =
+                get_expected_perfect_clear_probability(config, &state_after_place, table);
+            (placement, probability)
+        })
+        .fold(None, |best, (placement, probability)| match best {
+            Some((_, best_probability)) if best_probability >= probability => best,
+            _ => Some((placement, probability)),
         })
-        .collect()
 }
 
-fn branch_game_on_hold(config: &Config, game: &Game) -> Vec<Game> {
-    [true, false]
+/// The probability of a perfect clear from `state` if the best placement is always made, with
+/// known pieces as certain and unknown pieces weighted by the configured randomizer.
+fn get_expected_perfect_clear_probability(
+    config: &Config,
+    state: &State,
+    table: &mut TranspositionTable,
+) -> f32 {
+    if state.game.board.can_perfect_clear() {
+        return 1.0;
+    }
+    if is_dead_board(config, &state.game.board, state.moves_remaining) {
+        return 0.0;
+    }
+
+    let key = state.key();
+    if let Some(probability) = table.get(&key) {
+        return probability;
+    }
+
+    let probability = branch_state_for_piece(config, state)
+        .into_iter()
+        .map(|(state_with_piece, probability)| {
+            let place_probability = branch_state_to_placed_pieces(config, &state_with_piece, 1.0)
+                .into_iter()
+                .map(|(state_after_place, _)| {
+                    get_expected_perfect_clear_probability(config, &state_after_place, table)
+                })
+                .fold(0.0, f32::max);
+            let hold_probability = get_expected_hold_probability(config, &state_with_piece, table);
+            probability * place_probability.max(hold_probability)
+        })
+        .sum();
+    table.insert(key, probability);
+    probability
+}
+
+/// The probability of a perfect clear as in [`get_expected_perfect_clear_probability`] after the
+/// active piece of `state` is held into an empty hold while nothing is queued.
+fn get_expected_hold_probability(
+    config: &Config,
+    state: &State,
+    table: &mut TranspositionTable,
+) -> f32 {
+    branch_state_on_hold_into_unknown(config, state)
+        .into_iter()
+        .map(|(state_after_hold, probability)| {
+            probability * get_expected_perfect_clear_probability(config, &state_after_hold, table)
+        })
+        .sum()
+}
+
+/// Every sequence of `length` piece kinds which can be dealt after `history`, paired with the
+/// probability of it being dealt.
+fn get_piece_sequences(
+    config: &Config,
+    history: &PieceHistory,
+    length: usize,
+) -> Vec<(Vec<PieceKind>, f32)> {
+    if length == 0 {
+        return vec![(vec![], 1.0)];
+    }
+    let probabilities = config.randomizer.next_piece_probabilities(history);
+    PIECE_KINDS
         .iter()
-        .filter_map(|&switch| game.reduce(config, &GameAction::Hold { switch }).ok())
+        .filter(|kind| probabilities.get(kind) > 0.0)
+        .flat_map(|kind| {
+            let next_history = config.randomizer.with_seen_piece_kind(history, kind);
+            get_piece_sequences(config, &next_history, length - 1)
+                .into_iter()
+                .map(move |(mut rest, probability)| {
+                    rest.insert(0, *kind);
+                    (rest, probabilities.get(kind) * probability)
+                })
+        })
         .collect()
 }
 
-type PlaceablePiecesKey = (Point, Orientation);
+/// Give a state an active piece from its queue, or otherwise from the front of `sequence`. The
+/// queue is kept from running out while `sequence` has pieces left, so that the active piece can
+/// be held into an empty hold.
+fn next_state_with_piece<'a>(
+    config: &Config,
+    state: &State,
+    sequence: &'a [PieceKind],
+) -> Option<(State, &'a [PieceKind])> {
+    let (state, sequence) = with_queue_from_sequence(state.clone(), sequence);
+    let state_with_piece = if state.game.piece.is_some() {
+        state
+    } else {
+        spawn_next_piece(config, &state, |initial| Action::ConsumeQueue { initial })?
+    };
+    Some(with_queue_from_sequence(state_with_piece, sequence))
+}
 
-struct PlaceablePiecesValue {
-    is_placable: bool,
-    previous_key: Option<PlaceablePiecesKey>,
+/// Move the front of `sequence` into the queue of `state` if the queue is empty.
+fn with_queue_from_sequence(state: State, sequence: &[PieceKind]) -> (State, &[PieceKind]) {
+    match sequence.split_first() {
+        Some((&kind, rest_sequence)) if state.game.queue[0].is_none() => {
+            let mut queue = state.game.queue;
+            queue[0] = Some(kind);
+            let state = State {
+                game: Game {
+                    queue,
+                    ..state.game
+                },
+                ..state
+            };
+            (state, rest_sequence)
+        }
+        _ => (state, sequence),
+    }
 }
 
-fn branch_game_to_placable_pieces(config: &Config, game: &Game) -> Vec<Game> {
-    let piece = game.piece.unwrap();
-    let mut memo = HashMap::new();
+/// Whether a perfect clear can be reached from `state` if `sequence` is dealt after the queue.
+/// States which cannot reach a perfect clear are remembered in `failed_states`.
+fn has_perfect_clear(
+    config: &Config,
+    state: &State,
+    sequence: &[PieceKind],
+    failed_states: &mut HashSet<(Board, Option<PieceKind>, u8)>,
+) -> bool {
+    if state.game.board.can_perfect_clear() {
+        return true;
+    }
+    if is_dead_board(config, &state.game.board, state.moves_remaining) {
+        return false;
+    }
+
+    // The moves remaining determine how much of the queue and sequence is left.
+    let key = (
+        state.game.board,
+        state.game.hold_kind,
+        state.moves_remaining,
+    );
+    if failed_states.contains(&key) {
+        return false;
+    }
 
-    generate_placable_pieces(config, game, &mut memo);
+    let has_perfect_clear = next_state_with_piece(config, state, sequence).is_some_and(
+        |(state_with_piece, rest_sequence)| {
+            branch_state_to_placed_pieces(config, &state_with_piece, 1.0)
+                .into_iter()
+                .any(|(state_after_place, _)| {
+                    has_perfect_clear(config, &state_after_place, rest_sequence, failed_states)
+                })
+        },
+    );
+    if !has_perfect_clear {
+        failed_states.insert(key);
+    }
+    has_perfect_clear
+}
 
-    memo.into_iter()
-        .filter_map(|(k, v)| if v.is_placable { Some(k) } else { None })
-        .map(|(position, orientation)| Game {
-            piece: Some(Piece {
-                orientation,
-                position,
-                ..piece
-            }),
-            ..game.clone()
+/// Whether the board can no longer become a perfect clear of at most the configured height within
+/// the moves remaining. Perfect clears which need a piece to stick out above the perfect clear
+/// lines are not searched.
+///
+/// Holes which no piece can reach are not ruled out, since clearing a line above them can open
+/// them up again. Only the search can tell whether such a line can be cleared in time.
+fn is_dead_board(config: &Config, board: &Board, moves_remaining: u8) -> bool {
+    !(board.stack_height().max(1)..=config.perfect_clear_height)
+        .any(|height| board.can_perfect_clear_within(height, moves_remaining as usize))
+}
+
+/// Branch a state into every state with an active piece, paired with the probability of
+/// receiving that piece.
+fn branch_state_for_piece(config: &Config, state: &State) -> Vec<(State, f32)> {
+    if state.game.piece.is_some() {
+        return vec![(state.clone(), 1.0)];
+    }
+    if let Some(state_after_consume_queue) =
+        spawn_next_piece(config, state, |initial| Action::ConsumeQueue { initial })
+    {
+        return vec![(state_after_consume_queue, 1.0)];
+    }
+    let probabilities = state.next_piece_probabilities(config);
+    PIECE_KINDS
+        .iter()
+        .filter(|kind| probabilities.get(kind) > 0.0)
+        .filter_map(|&kind| {
+            spawn_next_piece(config, state, |initial| Action::WithNextPiece {
+                kind,
+                initial,
+            })
+            .map(|state| (state, probabilities.get(&kind)))
         })
         .collect()
 }
 
-/// For a given board and piece kind, each piece position and rotation should be memoized.
+/// Branch a state whose active piece can be held into an empty hold while nothing is queued into
+/// every state after holding with a guessed next piece, paired with the probability of receiving
+/// that piece. Holding into an empty hold with a queued piece is branched on when placing pieces.
+fn branch_state_on_hold_into_unknown(config: &Config, state: &State) -> Vec<(State, f32)> {
+    if state.game.hold_kind.is_some() || state.game.queue[0].is_some() {
+        return vec![];
+    }
+    let Ok(state_after_hold) =
+        state.reduce(config, &Action::Play(GameAction::Hold { switch: true }))
+    else {
+        return vec![];
+    };
+    branch_state_for_piece(config, &state_after_hold)
+}
+
+/// Reduce `state` with the action which spawns its next piece, without initial actions if the
+/// piece can spawn, and otherwise with the first initial rotation which lets it spawn. Other
+/// initial actions are branched on when the piece is placed.
+fn spawn_next_piece(
+    config: &Config,
+    state: &State,
+    spawn_action: impl Fn(InitialActions) -> Action,
+) -> Option<State> {
+    iter::once(None)
+        .chain(config.possible_initial_rotations().into_iter().map(Some))
+        .find_map(|rotation| {
+            let initial = InitialActions {
+                rotation,
+                hold: false,
+            };
+            state.reduce(config, &spawn_action(initial)).ok()
+        })
+}
+
+/// Branch a state with an active piece into every state after the piece is placed, paired with
+/// the placement made. `probability` is the probability of receiving the active piece.
 ///
-/// `self.piece` must be `Some` variant.
-fn generate_placable_pieces(
+/// Holding into an empty hold places the next piece in the queue instead, so it is only branched
+/// into when the queue is not empty.
+fn branch_state_to_placed_pieces(
     config: &Config,
-    game: &Game,
-    memo: &mut HashMap<PlaceablePiecesKey, PlaceablePiecesValue>,
-) {
-    let piece = game.piece.unwrap();
+    state: &State,
+    probability: f32,
+) -> Vec<(State, Placement)> {
+    branch_game_on_spawn(config, &state.game)
+        .into_iter()
+        .filter_map(|(game_after_spawn, initial_rotation, is_hold_used)| {
+            let state_after_spawn = State {
+                game: game_after_spawn,
+                ..state.clone()
+            };
+            if state_after_spawn.game.piece.is_some() {
+                return Some((state_after_spawn, initial_rotation, is_hold_used));
+            }
+            spawn_next_piece(config, &state_after_spawn, |initial| Action::ConsumeQueue {
+                initial,
+            })
+            .map(|state_after_spawn| (state_after_spawn, initial_rotation, is_hold_used))
+        })
+        .flat_map(|(state_after_spawn, initial_rotation, is_hold_used)| {
+            branch_game_to_placable_pieces(config, &state_after_spawn.game)
+                .into_iter()
+                .map(move |(game_after_move, moves)| {
+                    let state_after_move = State {
+                        game: game_after_move,
+                        ..state_after_spawn.clone()
+                    };
+                    (state_after_move, moves, initial_rotation, is_hold_used)
+                })
+        })
+        .map(
+            |(state_after_move, moves, initial_rotation, is_hold_used)| {
+                let (state_after_place, outcome) = state_after_move
+                    .reduce_with_outcome(config, &Action::Play(GameAction::Place))
+                    .unwrap();
+                let placement = Placement {
+                    piece: state_after_move.game.piece.unwrap(),
+                    initial_rotation,
+                    moves,
+                    is_hold_used,
+                    board: state_after_place.game.board,
+                    outcome: outcome.unwrap(),
+                    probability,
+                };
+                (state_after_place, placement)
+            },
+        )
+        .collect()
+}
 
-    config
-        .possible_moves()
-        .iter()
-        .filter_map(|&mov| game.reduce(config, &GameAction::Move(mov)).ok())
-        .for_each(|next_game| {
-            let next_piece = next_game.piece.unwrap();
-            let key = (next_piece.position, next_piece.orientation);
-            if memo.contains_key(&key) {
-                // TODO relax path
-            } else {
-                // set memo and continue branching
-                memo.entry((next_piece.position, next_piece.orientation))
-                    .or_insert(PlaceablePiecesValue {
-                        is_placable: next_game.board.can_place(&next_piece.get_points(config)),
-                        previous_key: Some((piece.position, piece.orientation)),
-                    });
-                generate_placable_pieces(config, &next_game, memo);
+/// Branch a game on the rotation held as its active piece spawned, and on whether the active
+/// piece is switched with the hold piece. Each game is paired with the initial rotation and
+/// whether hold was used.
+fn branch_game_on_spawn(config: &Config, game: &Game) -> Vec<(Game, Option<Rotation>, bool)> {
+    // A piece dealt after holding into an empty hold can only be placed.
+    if game.is_hold_used {
+        return vec![(game.clone(), None, true)];
+    }
+    let kind = game.piece.unwrap().kind;
+    let unspawned_game = Game {
+        piece: None,
+        ..game.clone()
+    };
+    let initial_rotations = iter::once(None)
+        .chain(config.possible_initial_rotations().into_iter().map(Some))
+        .collect::<Vec<_>>();
+    let spawn = |rotation: Option<Rotation>, hold: bool| {
+        unspawned_game
+            .with_spawned_piece(config, &kind, &InitialActions { rotation, hold })
+            .ok()
+    };
+
+    // Initial actions can only be held before the active piece has moved.
+    let is_spawned_piece = !game.is_hold_used
+        && initial_rotations
+            .iter()
+            .any(|&rotation| spawn(rotation, false).as_ref() == Some(game));
+    if !is_spawned_piece {
+        return branch_game_on_hold(config, game)
+            .into_iter()
+            .map(|(game, is_hold_used)| (game, None, is_hold_used))
+            .collect();
+    }
+
+    let mut games: Vec<(Game, Option<Rotation>, bool)> = vec![];
+    for rotation in initial_rotations {
+        let spawned_game = spawn(rotation, false);
+        // Without initial hold, the hold piece can only be switched in after spawning unrotated.
+        let held_game = if config.initial_hold {
+            spawn(rotation, true)
+        } else {
+            spawned_game
+                .as_ref()
+                .filter(|_| rotation.is_none())
+                .and_then(|game| game.reduce(config, &GameAction::Hold { switch: true }).ok())
+        };
+        for (game, is_hold_used) in [(held_game, true), (spawned_game, false)] {
+            let Some(game) = game else {
+                continue;
+            };
+            // Pieces which cannot spawn rotated spawn as they would without an initial rotation.
+            if games.iter().any(|(other_game, _, _)| *other_game == game) {
+                continue;
             }
-        });
+            games.push((game, rotation, is_hold_used));
+        }
+    }
+    games
+}
+
+/// Branch a game on whether the active piece is switched with the hold piece.
+fn branch_game_on_hold(config: &Config, game: &Game) -> Vec<(Game, bool)> {
+    [true, false]
+        .iter()
+        .filter_map(|&switch| {
+            game.reduce(config, &GameAction::Hold { switch })
+                .ok()
+                .map(|game| (game, switch))
+        })
+        .collect()
+}
+
+/// Branch a game into every placable piece, paired with the fewest moves needed to reach the
+/// placement from the active piece. The moves are replayed so that spins are recognised when the
+/// piece is placed.
+fn branch_game_to_placable_pieces(config: &Config, game: &Game) -> Vec<(Game, Vec<Move>)> {
+    generate_lock_positions(config, &game.board, &game.piece.unwrap())
+        .lock_positions
+        .into_iter()
+        .map(|lock_position| {
+            let game_after_moves = lock_position
+                .moves
+                .iter()
+                .try_fold(game.clone(), |game, &mov| {
+                    game.reduce(config, &GameAction::Move(mov))
+                })
+                .unwrap();
+            (game_after_moves, lock_position.moves)
+        })
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::piece::PieceKind;
+    use crate::config::{Gravity, Randomizer, Spawn};
+    use crate::piece::Piece;
+    use crate::test_utils::{board_with_empty_columns, history_with_seen, queue_of};
+    use crate::utils::direction::Direction;
+    use crate::utils::point::Point;
+    use crate::utils::rotation::{Orientation, Rotation};
 
     use super::*;
 
@@ -280,68 +859,1360 @@ mod tests {
             let next_games = branch_game_to_placable_pieces(&CONFIG, &game);
             let next_pieces = next_games
                 .into_iter()
-                .filter_map(|game| game.piece)
+                .filter_map(|(game, _)| game.piece)
                 .collect::<Vec<_>>();
 
-            // 10 each for east and west
-            // 7 each for north and south
-            assert_eq!(next_pieces.len(), 10 + 10 + 7 + 7);
+            // 10 for east or west
+            // 7 for north or south
+            assert_eq!(next_pieces.len(), 10 + 7);
+
+            let has_either = |first: Piece, second: Piece| {
+                next_pieces.contains(&first) ^ next_pieces.contains(&second)
+            };
 
             for horizontal_x in 0..7 {
-                // North
-                let expected_piece = Piece {
+                let north_piece = Piece {
                     kind: PieceKind::I,
                     orientation: Orientation::North,
                     position: Point::new(horizontal_x, -2),
                 };
-                assert!(next_pieces.contains(&expected_piece));
-
-                // South
-                let expected_piece = Piece {
+                let south_piece = Piece {
                     kind: PieceKind::I,
                     orientation: Orientation::South,
                     position: Point::new(horizontal_x, -1),
                 };
-                assert!(next_pieces.contains(&expected_piece));
+                assert!(has_either(north_piece, south_piece));
             }
 
             for vertical_x in 0..10 {
-                // East
-                let expected_piece = Piece {
+                let east_piece = Piece {
                     kind: PieceKind::I,
                     orientation: Orientation::East,
                     position: Point::new(vertical_x - 2, 0),
                 };
-                assert!(next_pieces.contains(&expected_piece));
-
-                // West
-                let expected_piece = Piece {
+                let west_piece = Piece {
                     kind: PieceKind::I,
                     orientation: Orientation::West,
                     position: Point::new(vertical_x - 1, 0),
                 };
-                assert!(next_pieces.contains(&expected_piece));
+                assert!(has_either(east_piece, west_piece));
+            }
+        }
+
+        fn replay_moves(config: &Config, game: &Game, moves: &[Move]) -> Game {
+            moves.iter().fold(game.clone(), |game, &mov| {
+                game.reduce(config, &GameAction::Move(mov))
+                    .expect("Expected every move to be valid")
+            })
+        }
+
+        #[test]
+        fn moves_reach_each_placement() {
+            let game = Game {
+                piece: Some(Piece::spawn(&CONFIG, &PieceKind::T)),
+                ..Game::initial()
+            };
+
+            for (next_game, moves) in branch_game_to_placable_pieces(&CONFIG, &game) {
+                assert_eq!(replay_moves(&CONFIG, &game, &moves).piece, next_game.piece);
+            }
+        }
+
+        #[test]
+        fn fewest_moves_to_placement() {
+            let game = Game {
+                piece: Some(Piece::spawn(&CONFIG, &PieceKind::I)),
+                ..Game::initial()
+            };
+
+            let next_games = branch_game_to_placable_pieces(&CONFIG, &game);
+
+            let moves_to = |orientation, position| {
+                next_games
+                    .iter()
+                    .find(|(game, _)| {
+                        let piece = game.piece.unwrap();
+                        piece.orientation == orientation && piece.position == position
+                    })
+                    .map(|(_, moves)| moves.clone())
+                    .unwrap()
+            };
+
+            assert_eq!(
+                moves_to(Orientation::North, Point::new(3, -2)),
+                vec![Move::Drop]
+            );
+            assert_eq!(
+                moves_to(Orientation::North, Point::new(0, -2)),
+                vec![
+                    Move::Translate(Direction::Left),
+                    Move::Translate(Direction::Left),
+                    Move::Translate(Direction::Left),
+                    Move::Drop,
+                ]
+            );
+            // Rotating clockwise moves the I piece 2 columns right of its spawn column.
+            assert_eq!(moves_to(Orientation::East, Point::new(7, 0)).len(), 6);
+        }
+
+        mod with_overhang {
+            use super::*;
+
+            fn game_with_overhang() -> Game {
+                let mut board = Board::empty_board();
+                for x in 0..3 {
+                    board.fill(&Point::new(x, 1));
+                }
+                Game {
+                    board,
+                    piece: Some(Piece::spawn(&CONFIG, &PieceKind::I)),
+                    ..Game::initial()
+                }
+            }
+
+            fn tucked_piece() -> Piece {
+                Piece {
+                    kind: PieceKind::I,
+                    orientation: Orientation::North,
+                    position: Point::new(0, -2),
+                }
+            }
+
+            #[test]
+            fn cannot_tuck_without_soft_drop() {
+                let game = game_with_overhang();
+
+                let next_games = branch_game_to_placable_pieces(&CONFIG, &game);
+
+                assert!(next_games
+                    .iter()
+                    .all(|(game, _)| game.piece != Some(tucked_piece())));
+            }
+
+            #[test]
+            fn tucks_with_soft_drop() {
+                let config = Config {
+                    soft_drop_allowed: true,
+                    ..Config::default()
+                };
+                let game = game_with_overhang();
+
+                let next_games = branch_game_to_placable_pieces(&config, &game);
+
+                let (_, moves) = next_games
+                    .iter()
+                    .find(|(game, _)| game.piece == Some(tucked_piece()))
+                    .expect("Expected I piece to tuck under the overhang");
+                assert_eq!(
+                    moves,
+                    &vec![
+                        Move::Drop,
+                        Move::Translate(Direction::Left),
+                        Move::Translate(Direction::Left),
+                        Move::Translate(Direction::Left),
+                    ]
+                );
+                assert_eq!(
+                    replay_moves(&config, &game, moves).piece,
+                    Some(tucked_piece())
+                );
             }
         }
     }
 
-    mod tests {
+    /// Keep the solutions which never hold. Holding into the empty hold in these tests leads to
+    /// other solutions which place guessed pieces.
+    fn without_hold(solutions: Vec<Solution>) -> Vec<Solution> {
+        solutions
+            .into_iter()
+            .filter(|solution| {
+                !solution
+                    .placements
+                    .iter()
+                    .any(|placement| placement.is_hold_used)
+            })
+            .collect()
+    }
+
+    mod get_perfect_clear_paths {
         use super::*;
 
         #[test]
-        fn test() {
+        fn no_paths_if_no_moves_remaining() {
             let state = State {
                 game: Game {
-                    piece: Some(Piece::spawn(&CONFIG, &PieceKind::I)),
-                    queue: PIECE_KINDS.map(|kind| Some(kind)),
+                    board: board_with_empty_columns(&[9]),
+                    queue: queue_of(&[PieceKind::I]),
                     ..Game::initial()
                 },
+                moves_remaining: 0,
                 ..State::initial()
             };
-            let results = get_perfect_clear_paths(&CONFIG, &state);
-            // for result in results {
-            //     println!("{:?}", result);
-            // }
+
+            let solutions = get_perfect_clear_paths(&CONFIG, &state);
+
+            assert!(solutions.is_empty());
+        }
+
+        #[test]
+        fn initial_rotation_spawns_blocked_piece() {
+            let mut board = Board::empty_board();
+            for y in 0..3 {
+                for x in 0..10 {
+                    board.fill(&Point::new(x, y));
+                }
+            }
+            for (x, y) in [(4, 0), (4, 1), (4, 2), (5, 1)] {
+                board.empty(&Point::new(x, y));
+            }
+            let state = State {
+                game: Game {
+                    board,
+                    queue: queue_of(&[PieceKind::T]),
+                    ..Game::initial()
+                },
+                moves_remaining: 1,
+                ..State::initial()
+            };
+            // The T piece spawns on the stack, where it only fits facing east.
+            let config = Config {
+                spawn: Spawn {
+                    row: -19,
+                    ..Spawn::default()
+                },
+                perfect_clear_height: 3,
+                ..Config::default()
+            };
+            let irs_config = Config {
+                initial_rotation: true,
+                ..config.clone()
+            };
+
+            let solutions = get_perfect_clear_paths(&config, &state);
+            let irs_solutions = get_perfect_clear_paths(&irs_config, &state);
+
+            assert!(solutions.is_empty());
+            assert_eq!(irs_solutions.len(), 1);
+            let placement = &irs_solutions[0].placements[0];
+            assert_eq!(placement.initial_rotation, Some(Rotation::Clockwise));
+            assert!(placement.moves.is_empty());
+        }
+
+        #[test]
+        fn no_paths_from_dead_board() {
+            let mut board = board_with_empty_columns(&[9]);
+            board.fill(&Point::new(9, 3));
+            let state = State {
+                game: Game {
+                    board,
+                    queue: queue_of(&[PieceKind::I, PieceKind::O, PieceKind::T]),
+                    ..Game::initial()
+                },
+                ..State::initial()
+            };
+
+            let solutions = get_perfect_clear_paths(&CONFIG, &state);
+
+            assert!(solutions.is_empty());
+        }
+
+        #[test]
+        fn path_through_enclosed_hole() {
+            let config = Config {
+                perfect_clear_height: 3,
+                ..Config::default()
+            };
+            let mut board = Board::empty_board();
+            for x in 2..10 {
+                board.fill(&Point::new(x, 0));
+            }
+            for x in 0..6 {
+                board.fill(&Point::new(x, 1));
+            }
+            let state = State {
+                game: Game {
+                    board,
+                    queue: queue_of(&[PieceKind::I, PieceKind::O, PieceKind::I, PieceKind::I]),
+                    ..Game::initial()
+                },
+                ..State::initial()
+            }
+            .with_perfect_clear_budget(&config);
+
+            let solutions = without_hold(get_perfect_clear_paths(&config, &state));
+
+            // The hole in the first line is closed off until the second line is cleared.
+            let expected_pieces = [
+                (PieceKind::I, Point::new(6, -1)),
+                (PieceKind::O, Point::new(-1, -1)),
+                (PieceKind::I, Point::new(2, -2)),
+                (PieceKind::I, Point::new(6, -2)),
+            ]
+            .map(|(kind, position)| Piece {
+                kind,
+                orientation: Orientation::North,
+                position,
+            });
+            assert!(solutions.iter().any(|solution| solution
+                .placements
+                .iter()
+                .map(|placement| placement.piece)
+                .eq(expected_pieces)));
+        }
+
+        #[test]
+        fn no_paths_above_perfect_clear_height() {
+            let config = Config {
+                perfect_clear_height: 2,
+                ..Config::default()
+            };
+            let state = State {
+                game: Game {
+                    board: board_with_empty_columns(&[9]),
+                    queue: queue_of(&[PieceKind::I]),
+                    ..Game::initial()
+                },
+                moves_remaining: 1,
+                ..State::initial()
+            };
+
+            let solutions = get_perfect_clear_paths(&config, &state);
+
+            assert!(solutions.is_empty());
+        }
+
+        #[test]
+        fn two_line_height() {
+            let config = Config {
+                perfect_clear_height: 2,
+                ..Config::default()
+            };
+            let mut board = Board::empty_board();
+            for y in 0..2 {
+                for x in 0..8 {
+                    board.fill(&Point::new(x, y));
+                }
+            }
+            let state = State {
+                game: Game {
+                    board,
+                    queue: queue_of(&[PieceKind::O]),
+                    ..Game::initial()
+                },
+                ..State::initial()
+            }
+            .with_perfect_clear_budget(&config);
+
+            let solutions = get_perfect_clear_paths(&config, &state);
+
+            assert_eq!(state.moves_remaining, 1);
+            assert_eq!(solutions.len(), 1);
+            assert_eq!(solutions[0].board(), Some(Board::PC_BOARDS[1]));
+        }
+
+        #[test]
+        fn four_line_single_piece() {
+            let board = board_with_empty_columns(&[9]);
+            let state = State {
+                game: Game {
+                    board,
+                    queue: queue_of(&[PieceKind::I]),
+                    ..Game::initial()
+                },
+                moves_remaining: 1,
+                ..State::initial()
+            };
+
+            let solutions = get_perfect_clear_paths(&CONFIG, &state);
+
+            assert_eq!(solutions.len(), 1);
+            let placements = &solutions[0].placements;
+            assert_eq!(placements.len(), 1);
+
+            let placement = &placements[0];
+            assert_eq!(placement.piece.kind, PieceKind::I);
+            assert!(!placement.is_hold_used);
+            assert_eq!(placement.probability, 1.0);
+            assert!(placement.board.can_perfect_clear());
+            assert_eq!(
+                placement.moves,
+                vec![
+                    Move::Rotate(Rotation::Clockwise),
+                    Move::Translate(Direction::Right),
+                    Move::Translate(Direction::Right),
+                    Move::Translate(Direction::Right),
+                    Move::Translate(Direction::Right),
+                    Move::Drop,
+                ]
+            );
+            for point in placement.piece.get_points(&CONFIG) {
+                assert_eq!(point.x, 9, "Expected I piece to fill the last column");
+            }
+        }
+
+        #[test]
+        fn four_line_single_piece_with_twenty_gravity() {
+            let config = Config {
+                gravity: Gravity::TwentyG,
+                ..Config::default()
+            };
+            let state = State {
+                game: Game {
+                    board: board_with_empty_columns(&[9]),
+                    queue: queue_of(&[PieceKind::I]),
+                    ..Game::initial()
+                },
+                moves_remaining: 1,
+                ..State::initial()
+            };
+
+            let solutions = get_perfect_clear_paths(&config, &state);
+
+            assert_eq!(solutions.len(), 1);
+            let placement = &solutions[0].placements[0];
+            assert!(placement.board.can_perfect_clear());
+            // The I piece sinks into the well as soon as it is moved over it.
+            assert_eq!(
+                placement.moves.last(),
+                Some(&Move::Translate(Direction::Right))
+            );
+            assert!(!placement.moves.contains(&Move::Drop));
+        }
+
+        #[test]
+        fn four_line_with_hold() {
+            let state = State {
+                game: Game {
+                    board: board_with_empty_columns(&[9]),
+                    piece: Some(Piece::spawn(&CONFIG, &PieceKind::O)),
+                    hold_kind: Some(PieceKind::I),
+                    ..Game::initial()
+                },
+                moves_remaining: 1,
+                ..State::initial()
+            };
+
+            let solutions = get_perfect_clear_paths(&CONFIG, &state);
+
+            assert_eq!(solutions.len(), 1);
+            let placement = &solutions[0].placements[0];
+            assert_eq!(placement.piece.kind, PieceKind::I);
+            assert!(placement.is_hold_used);
+        }
+
+        #[test]
+        fn four_line_known_queue() {
+            let board = board_with_empty_columns(&[0, 1, 9]);
+            let state = State {
+                game: Game {
+                    board,
+                    piece: Some(Piece::spawn(&CONFIG, &PieceKind::I)),
+                    queue: queue_of(&[PieceKind::O, PieceKind::O]),
+                    ..Game::initial()
+                },
+                moves_remaining: 3,
+                ..State::initial()
+            };
+
+            let solutions = without_hold(get_perfect_clear_paths(&CONFIG, &state));
+
+            let board_after_i = board_with_empty_columns(&[0, 1]);
+            // The first O piece clears the bottom 2 lines.
+            let mut board_after_o = Board::empty_board();
+            for y in 0..2 {
+                for x in 2..10 {
+                    board_after_o.fill(&Point::new(x, y));
+                }
+            }
+            let board_after_pc = {
+                let mut b = Board::empty_board();
+                for y in 0..2 {
+                    for x in 0..10 {
+                        b.fill(&Point::new(x, y));
+                    }
+                }
+                b
+            };
+
+            assert_eq!(solutions.len(), 1);
+            let placements = &solutions[0].placements;
+            assert_eq!(
+                placements
+                    .iter()
+                    .map(|placement| (placement.piece.kind, placement.board))
+                    .collect::<Vec<_>>(),
+                vec![
+                    (PieceKind::I, board_after_i),
+                    (PieceKind::O, board_after_o),
+                    (PieceKind::O, board_after_pc),
+                ]
+            );
+            assert_eq!(
+                placements[1].piece,
+                Piece {
+                    kind: PieceKind::O,
+                    position: Point::new(-1, -1),
+                    orientation: placements[1].piece.orientation,
+                }
+            );
+            assert_eq!(solutions[0].probability(), 1.0);
+        }
+
+        #[test]
+        fn four_line_guessed_piece() {
+            let board = board_with_empty_columns(&[0, 1, 9]);
+            let state = State {
+                game: Game {
+                    board,
+                    piece: Some(Piece::spawn(&CONFIG, &PieceKind::I)),
+                    queue: queue_of(&[PieceKind::O]),
+                    ..Game::initial()
+                },
+                // The O piece in the queue is the last piece of the bag.
+                piece_history: history_with_seen(&[
+                    PieceKind::I,
+                    PieceKind::J,
+                    PieceKind::L,
+                    PieceKind::S,
+                    PieceKind::T,
+                    PieceKind::Z,
+                ]),
+                moves_remaining: 3,
+            };
+
+            let solutions = without_hold(get_perfect_clear_paths(&CONFIG, &state));
+
+            assert_eq!(solutions.len(), 1);
+            let placements = &solutions[0].placements;
+            assert_eq!(
+                placements
+                    .iter()
+                    .map(|placement| placement.piece.kind)
+                    .collect::<Vec<_>>(),
+                vec![PieceKind::I, PieceKind::O, PieceKind::O]
+            );
+            assert_eq!(placements[2].probability, 1.0 / 7.0);
+            assert_eq!(solutions[0].probability(), 1.0 / 7.0);
+        }
+
+        #[test]
+        fn four_line_last_piece_in_bag() {
+            let state = State {
+                game: Game {
+                    board: board_with_empty_columns(&[9]),
+                    ..Game::initial()
+                },
+                piece_history: history_with_seen(&[
+                    PieceKind::J,
+                    PieceKind::L,
+                    PieceKind::O,
+                    PieceKind::S,
+                    PieceKind::T,
+                    PieceKind::Z,
+                ]),
+                moves_remaining: 1,
+            };
+
+            let solutions = get_perfect_clear_paths(&CONFIG, &state);
+
+            assert_eq!(solutions.len(), 1);
+            assert_eq!(solutions[0].placements[0].piece.kind, PieceKind::I);
+            assert_eq!(solutions[0].probability(), 1.0);
+        }
+
+        #[test]
+        fn no_paths_if_piece_already_seen_in_bag() {
+            let state = State {
+                game: Game {
+                    board: board_with_empty_columns(&[9]),
+                    ..Game::initial()
+                },
+                piece_history: history_with_seen(&[PieceKind::I]),
+                moves_remaining: 1,
+            };
+
+            let solutions = get_perfect_clear_paths(&CONFIG, &state);
+
+            assert!(solutions.is_empty());
+        }
+
+        #[test]
+        fn guesses_with_configured_randomizer() {
+            let config = Config {
+                randomizer: Randomizer::Random,
+                ..Config::default()
+            };
+            let state = State {
+                game: Game {
+                    board: board_with_empty_columns(&[9]),
+                    ..Game::initial()
+                },
+                piece_history: history_with_seen(&[PieceKind::I]),
+                moves_remaining: 1,
+            };
+
+            let solutions = get_perfect_clear_paths(&config, &state);
+
+            assert_eq!(solutions.len(), 1);
+            assert_eq!(solutions[0].probability(), 1.0 / 7.0);
+        }
+
+        #[test]
+        fn two_line_from_empty_board() {
+            let state = State {
+                game: Game {
+                    piece: Some(Piece::spawn(&CONFIG, &PieceKind::I)),
+                    queue: queue_of(&[PieceKind::I, PieceKind::O, PieceKind::O, PieceKind::O]),
+                    ..Game::initial()
+                },
+                moves_remaining: 5,
+                ..State::initial()
+            };
+
+            let solutions = without_hold(get_perfect_clear_paths(&CONFIG, &state));
+
+            assert!(!solutions.is_empty());
+            for solution in solutions {
+                assert_eq!(solution.placements.len(), 5);
+                assert_eq!(solution.board(), Some(Board::PC_BOARDS[1]));
+            }
+        }
+
+        #[test]
+        fn two_line_opener_from_empty_board() {
+            // Cover an I piece with the J and L pieces, and fill the rest with O pieces.
+            let config = Config {
+                perfect_clear_height: 2,
+                ..Config::default()
+            };
+            let state = State {
+                game: Game {
+                    piece: Some(Piece::spawn(&config, &PieceKind::J)),
+                    queue: queue_of(&[PieceKind::L, PieceKind::I, PieceKind::O, PieceKind::O]),
+                    ..Game::initial()
+                },
+                ..State::initial()
+            }
+            .with_perfect_clear_budget(&config);
+
+            let solutions = without_hold(get_perfect_clear_paths(&config, &state));
+
+            assert_eq!(state.game.board, Board::empty_board());
+            assert_eq!(state.moves_remaining, 5);
+            // The J, L and I pieces go against either wall or in the middle, and the O pieces can
+            // be placed in either order.
+            assert_eq!(solutions.len(), 6);
+            for solution in solutions.iter() {
+                assert_eq!(solution.probability(), 1.0);
+                assert!(solution.placements[4].outcome.is_perfect_clear);
+            }
+            let expected_pieces = [
+                (PieceKind::J, Point::new(0, -1)),
+                (PieceKind::L, Point::new(3, -1)),
+                (PieceKind::I, Point::new(1, -1)),
+                (PieceKind::O, Point::new(5, -1)),
+                (PieceKind::O, Point::new(7, -1)),
+            ]
+            .map(|(kind, position)| Piece {
+                kind,
+                orientation: Orientation::North,
+                position,
+            });
+            assert!(solutions.iter().any(|solution| solution
+                .placements
+                .iter()
+                .map(|placement| placement.piece)
+                .eq(expected_pieces)));
+        }
+
+        #[test]
+        fn four_line_opener_from_empty_board() {
+            // Open by stacking four I pieces flat against the left wall, and two O pieces against
+            // the right wall.
+            let mut state = State::initial();
+            for (kind, direction) in [
+                (PieceKind::I, Direction::Left),
+                (PieceKind::I, Direction::Left),
+                (PieceKind::I, Direction::Left),
+                (PieceKind::I, Direction::Left),
+                (PieceKind::O, Direction::Right),
+                (PieceKind::O, Direction::Right),
+            ] {
+                state = state
+                    .reduce(
+                        &CONFIG,
+                        &Action::WithNextPiece {
+                            kind,
+                            initial: InitialActions::NONE,
+                        },
+                    )
+                    .unwrap();
+                let translate = Action::Play(GameAction::Move(Move::Translate(direction)));
+                while let Ok(next_state) = state.reduce(&CONFIG, &translate) {
+                    state = next_state;
+                }
+                for action in [GameAction::Move(Move::Drop), GameAction::Place] {
+                    state = state.reduce(&CONFIG, &Action::Play(action)).unwrap();
+                }
+            }
+            let state = State {
+                game: Game {
+                    queue: queue_of(&[
+                        PieceKind::T,
+                        PieceKind::T,
+                        PieceKind::L,
+                        PieceKind::J,
+                        PieceKind::S,
+                    ]),
+                    ..state.game
+                },
+                ..state
+            };
+
+            let solutions = get_perfect_clear_paths(&CONFIG, &state);
+
+            assert_eq!(state.moves_remaining, 4);
+            assert!(!solutions.is_empty());
+            for solution in solutions.iter() {
+                assert_eq!(solution.placements.len(), 4);
+                assert_eq!(solution.probability(), 1.0);
+                assert!(solution.placements[3].outcome.is_perfect_clear);
+            }
+            // The T, L and J pieces fill the middle.
+            let expected_pieces = [
+                (PieceKind::T, Orientation::West, Point::new(6, 0)),
+                (PieceKind::T, Orientation::East, Point::new(3, 0)),
+                (PieceKind::L, Orientation::West, Point::new(4, 0)),
+                (PieceKind::J, Orientation::East, Point::new(5, 0)),
+            ]
+            .map(|(kind, orientation, position)| Piece {
+                kind,
+                orientation,
+                position,
+            });
+            assert!(solutions.iter().any(|solution| solution
+                .placements
+                .iter()
+                .map(|placement| placement.piece)
+                .eq(expected_pieces)));
+        }
+
+        #[test]
+        #[ignore = "an exhaustive search from an empty board takes minutes"]
+        fn four_line_from_empty_board() {
+            let state = State {
+                game: Game {
+                    piece: Some(Piece::spawn(&CONFIG, &PieceKind::I)),
+                    queue: PIECE_KINDS.map(Some),
+                    ..Game::initial()
+                },
+                ..State::initial()
+            };
+
+            let solutions = get_perfect_clear_paths(&CONFIG, &state);
+
+            assert!(!solutions.is_empty());
+        }
+    }
+
+    mod with_garbage {
+        use super::*;
+
+        #[test]
+        fn one_garbage_line() {
+            let mut board = Board::empty_board();
+            for x in 3..10 {
+                board.fill(&Point::new(x, 0));
+            }
+            let config = Config {
+                perfect_clear_height: 2,
+                ..Config::default()
+            };
+            let state = State {
+                game: Game {
+                    board,
+                    queue: queue_of(&[PieceKind::L]),
+                    ..Game::initial()
+                },
+                ..State::initial()
+            }
+            .reduce(
+                &config,
+                &Action::Play(GameAction::ReceiveGarbage {
+                    line_count: 1,
+                    hole_column: 0,
+                }),
+            )
+            .unwrap()
+            .with_perfect_clear_budget(&config);
+
+            let solutions = get_perfect_clear_paths(&config, &state);
+
+            assert_eq!(state.moves_remaining, 1);
+            assert_eq!(solutions.len(), 1);
+            let placement = &solutions[0].placements[0];
+            assert_eq!(placement.piece.orientation, Orientation::South);
+            assert_eq!(placement.outcome.cleared_rows, vec![0, 1]);
+            assert!(placement.outcome.is_perfect_clear);
+        }
+
+        #[test]
+        fn four_garbage_lines() {
+            let config = Config {
+                perfect_clear_height: 4,
+                ..Config::default()
+            };
+            let state = State {
+                game: Game {
+                    hold_kind: Some(PieceKind::I),
+                    queue: queue_of(&[PieceKind::O]),
+                    ..Game::initial()
+                },
+                moves_remaining: 1,
+                ..State::initial()
+            }
+            .reduce(
+                &config,
+                &Action::Play(GameAction::ReceiveGarbage {
+                    line_count: 4,
+                    hole_column: 6,
+                }),
+            )
+            .unwrap();
+
+            let solutions = get_perfect_clear_paths(&config, &state);
+
+            // The O piece is switched with the held I piece, which fills the hole.
+            assert_eq!(solutions.len(), 1);
+            let placement = &solutions[0].placements[0];
+            assert_eq!(placement.piece.kind, PieceKind::I);
+            assert!(placement.is_hold_used);
+            assert_eq!(solutions[0].board(), Some(Board::PC_BOARDS[3]));
+        }
+    }
+
+    mod get_perfect_clear_paths_by_score {
+        use crate::game::Spin;
+
+        use super::*;
+
+        fn config_with_height(perfect_clear_height: isize) -> Config {
+            Config {
+                soft_drop_allowed: true,
+                perfect_clear_height,
+                ..Config::default()
+            }
+        }
+
+        #[test]
+        fn ranks_equally_likely_paths_by_points() {
+            let mut board = Board::empty_board();
+            for y in 0..2 {
+                for x in 0..4 {
+                    board.fill(&Point::new(x, y));
+                }
+            }
+            let state = State {
+                game: Game {
+                    board,
+                    hold_kind: Some(PieceKind::O),
+                    queue: queue_of(&[PieceKind::I, PieceKind::I]),
+                    ..Game::initial()
+                },
+                moves_remaining: 3,
+                ..State::initial()
+            };
+
+            let solutions = get_perfect_clear_paths_by_score(&config_with_height(2), &state);
+
+            let points = solutions
+                .iter()
+                .map(|solution| solution.score(&Score::initial()).points)
+                .collect::<Vec<_>>();
+            assert!(points.windows(2).all(|pair| pair[0] >= pair[1]));
+            // Stacking both I pieces before switching in the O piece clears both lines at once.
+            assert_eq!(points.first(), Some(&(300 + 1200)));
+            assert_eq!(points.last(), Some(&(100 + 100 + 50 + 800)));
+        }
+
+        #[test]
+        fn scores_t_spin_double() {
+            let mut board = Board::empty_board();
+            for y in 0..3 {
+                for x in 0..10 {
+                    board.fill(&Point::new(x, y));
+                }
+            }
+            for (x, y) in [
+                (4, 0),
+                (3, 1),
+                (4, 1),
+                (5, 1),
+                (4, 2),
+                (5, 2),
+                (6, 2),
+                (7, 2),
+            ] {
+                board.empty(&Point::new(x, y));
+            }
+            let state = State {
+                game: Game {
+                    board,
+                    queue: queue_of(&[PieceKind::T, PieceKind::I]),
+                    ..Game::initial()
+                },
+                moves_remaining: 2,
+                ..State::initial()
+            };
+
+            let solutions = without_hold(get_perfect_clear_paths_by_score(
+                &config_with_height(3),
+                &state,
+            ));
+
+            assert_eq!(solutions.len(), 1);
+            let t_placement = &solutions[0].placements[0];
+            assert_eq!(t_placement.outcome.spin, Some(Spin::TSpin));
+            assert_eq!(t_placement.outcome.cleared_rows, vec![0, 1]);
+            assert_eq!(
+                solutions[0].score(&Score::initial()).points,
+                1200 + 100 + 50 + 800
+            );
+        }
+
+        #[test]
+        fn keeps_spin_into_same_cells() {
+            let mut board = Board::empty_board();
+            for (min_x, y) in [(1, 0), (3, 1), (4, 2)] {
+                for x in min_x..Board::WIDTH {
+                    board.fill(&Point::new(x, y));
+                }
+            }
+            let state = State {
+                game: Game {
+                    board,
+                    queue: queue_of(&[PieceKind::T, PieceKind::T]),
+                    ..Game::initial()
+                },
+                moves_remaining: 2,
+                ..State::initial()
+            };
+
+            let solutions = get_perfect_clear_paths_by_score(&config_with_height(3), &state);
+
+            // The first T piece can drop straight against the wall, or spin into place for a mini.
+            let t_placement = &solutions[0].placements[0];
+            assert_eq!(t_placement.outcome.spin, Some(Spin::TSpinMini));
+            assert!(solutions.iter().any(|solution| {
+                let placement = &solution.placements[0];
+                placement.piece == t_placement.piece && placement.outcome.spin.is_none()
+            }));
+        }
+    }
+
+    mod get_perfect_clear_paths_by_attack {
+        use crate::attack::AttackTable;
+
+        use super::*;
+
+        #[test]
+        fn ranks_equally_likely_paths_by_attack() {
+            let mut board = Board::empty_board();
+            for y in 0..2 {
+                for x in 0..4 {
+                    board.fill(&Point::new(x, y));
+                }
+            }
+            let state = State {
+                game: Game {
+                    board,
+                    hold_kind: Some(PieceKind::O),
+                    queue: queue_of(&[PieceKind::I, PieceKind::I]),
+                    ..Game::initial()
+                },
+                moves_remaining: 3,
+                ..State::initial()
+            };
+
+            // Tetris Friends sends a line for the combo, which makes up for the single.
+            for (attack_table, least_attack) in [
+                (AttackTable::TetrisFriends, 1 + 10),
+                (AttackTable::TetrIO, 10),
+                (AttackTable::PPT, 10),
+            ] {
+                let config = Config {
+                    perfect_clear_height: 2,
+                    attack_table,
+                    ..Config::default()
+                };
+
+                let solutions = get_perfect_clear_paths_by_attack(&config, &state);
+
+                let attacks = solutions
+                    .iter()
+                    .map(|solution| solution.attack(&attack_table, &Score::initial()))
+                    .collect::<Vec<_>>();
+                assert!(attacks.windows(2).all(|pair| pair[0] >= pair[1]));
+                // A perfect clear double sends more than a single followed by a perfect clear
+                // single.
+                assert_eq!(attacks.first(), Some(&(1 + 10)));
+                assert_eq!(attacks.last(), Some(&least_attack));
+            }
+        }
+    }
+
+    mod get_perfect_clear_percent {
+        use super::*;
+
+        #[test]
+        fn certain_if_already_perfect_cleared() {
+            let state = State {
+                game: Game {
+                    board: Board::PC_BOARDS[3],
+                    ..Game::initial()
+                },
+                ..State::initial()
+            };
+
+            let percent = get_perfect_clear_percent(&CONFIG, &state);
+
+            assert_eq!(percent.probability, 1.0);
+            assert!(percent.by_first_placement.is_empty());
+        }
+
+        #[test]
+        fn impossible_if_no_moves_remaining() {
+            let state = State {
+                game: Game {
+                    board: board_with_empty_columns(&[9]),
+                    queue: queue_of(&[PieceKind::I]),
+                    ..Game::initial()
+                },
+                moves_remaining: 0,
+                ..State::initial()
+            };
+
+            let percent = get_perfect_clear_percent(&CONFIG, &state);
+
+            assert_eq!(percent.probability, 0.0);
+        }
+
+        #[test]
+        fn certain_with_known_queue() {
+            let state = State {
+                game: Game {
+                    board: board_with_empty_columns(&[9]),
+                    queue: queue_of(&[PieceKind::I]),
+                    ..Game::initial()
+                },
+                moves_remaining: 1,
+                ..State::initial()
+            };
+
+            let percent = get_perfect_clear_percent(&CONFIG, &state);
+
+            assert_eq!(percent.probability, 1.0);
+            let (best_placement, best_probability) = percent.best_first_placement().unwrap();
+            assert_eq!(*best_probability, 1.0);
+            assert!(best_placement.board.can_perfect_clear());
+            for (placement, probability) in percent.by_first_placement.iter() {
+                assert_eq!(*probability > 0.0, placement.board.can_perfect_clear());
+            }
+        }
+
+        #[test]
+        fn certain_with_hold() {
+            let state = State {
+                game: Game {
+                    board: board_with_empty_columns(&[9]),
+                    piece: Some(Piece::spawn(&CONFIG, &PieceKind::O)),
+                    hold_kind: Some(PieceKind::I),
+                    ..Game::initial()
+                },
+                moves_remaining: 1,
+                ..State::initial()
+            };
+
+            let percent = get_perfect_clear_percent(&CONFIG, &state);
+
+            assert_eq!(percent.probability, 1.0);
+            let (best_placement, _) = percent.best_first_placement().unwrap();
+            assert!(best_placement.is_hold_used);
+            assert_eq!(best_placement.piece.kind, PieceKind::I);
+        }
+
+        #[test]
+        fn guessed_piece_after_holding_into_empty_hold() {
+            let state = State {
+                game: Game {
+                    board: board_with_empty_columns(&[9]),
+                    piece: Some(Piece::spawn(&CONFIG, &PieceKind::O)),
+                    ..Game::initial()
+                },
+                // Holding the O piece deals one of the other six pieces in the bag.
+                piece_history: history_with_seen(&[PieceKind::O]),
+                moves_remaining: 1,
+            };
+
+            let percent = get_perfect_clear_percent(&CONFIG, &state);
+
+            assert!((percent.probability - 1.0 / 6.0).abs() < 1e-6);
+            let (best_placement, _) = percent.best_first_placement().unwrap();
+            assert!(best_placement.is_hold_used);
+            assert_eq!(best_placement.piece.kind, PieceKind::I);
+        }
+
+        #[test]
+        fn guessed_piece_in_bag() {
+            let state = State {
+                game: Game {
+                    board: board_with_empty_columns(&[9]),
+                    ..Game::initial()
+                },
+                piece_history: history_with_seen(&[
+                    PieceKind::J,
+                    PieceKind::L,
+                    PieceKind::O,
+                    PieceKind::S,
+                    PieceKind::T,
+                ]),
+                moves_remaining: 1,
+            };
+
+            let percent = get_perfect_clear_percent(&CONFIG, &state);
+
+            // Whichever of the I and Z pieces is dealt first, the Z piece can be held.
+            assert_eq!(percent.probability, 1.0);
+            let (best_placement, best_probability) = percent.best_first_placement().unwrap();
+            assert_eq!(*best_probability, 0.5);
+            assert_eq!(best_placement.piece.kind, PieceKind::I);
+            assert_eq!(best_placement.probability, 0.5);
+        }
+
+        #[test]
+        fn guesses_with_configured_randomizer() {
+            let config = Config {
+                randomizer: Randomizer::Random,
+                ..Config::default()
+            };
+            let state = State {
+                game: Game {
+                    board: board_with_empty_columns(&[9]),
+                    ..Game::initial()
+                },
+                piece_history: history_with_seen(&[PieceKind::I]),
+                moves_remaining: 1,
+            };
+
+            let percent = get_perfect_clear_percent(&config, &state);
+
+            // The first piece can be held, so the I piece can be either of the next two pieces.
+            assert!((percent.probability - (1.0 - (6.0 / 7.0_f32).powi(2))).abs() < 1e-6);
+        }
+
+        #[test]
+        fn guessed_pieces_after_queue() {
+            let state = State {
+                game: Game {
+                    board: board_with_empty_columns(&[0, 1, 9]),
+                    piece: Some(Piece::spawn(&CONFIG, &PieceKind::I)),
+                    // The Z piece cannot help fill the empty columns.
+                    hold_kind: Some(PieceKind::Z),
+                    ..Game::initial()
+                },
+                // Only the O piece is left in the bag.
+                piece_history: history_with_seen(&[
+                    PieceKind::I,
+                    PieceKind::J,
+                    PieceKind::L,
+                    PieceKind::S,
+                    PieceKind::T,
+                    PieceKind::Z,
+                ]),
+                moves_remaining: 3,
+            };
+
+            let percent = get_perfect_clear_percent(&CONFIG, &state);
+
+            assert!((percent.probability - 1.0 / 7.0).abs() < 1e-6);
+            for (placement, probability) in percent.by_first_placement.iter() {
+                assert!(*probability == 0.0 || placement.piece.kind == PieceKind::I);
+                assert!(*probability <= percent.probability);
+            }
+        }
+    }
+
+    mod best_move {
+        use super::*;
+
+        #[test]
+        fn none_if_next_piece_unknown() {
+            let state = State {
+                game: Game {
+                    board: board_with_empty_columns(&[9]),
+                    ..Game::initial()
+                },
+                moves_remaining: 1,
+                ..State::initial()
+            };
+
+            assert_eq!(best_move(&CONFIG, &state), None);
+        }
+
+        #[test]
+        fn places_known_piece() {
+            let state = State {
+                game: Game {
+                    board: board_with_empty_columns(&[9]),
+                    queue: queue_of(&[PieceKind::I]),
+                    ..Game::initial()
+                },
+                moves_remaining: 1,
+                ..State::initial()
+            };
+
+            let (placement, probability) = best_move(&CONFIG, &state).unwrap();
+
+            assert_eq!(probability, 1.0);
+            assert_eq!(placement.piece.kind, PieceKind::I);
+            assert!(placement.board.can_perfect_clear());
+        }
+
+        #[test]
+        fn holds_if_better() {
+            let state = State {
+                game: Game {
+                    board: board_with_empty_columns(&[9]),
+                    piece: Some(Piece::spawn(&CONFIG, &PieceKind::O)),
+                    hold_kind: Some(PieceKind::I),
+                    ..Game::initial()
+                },
+                moves_remaining: 1,
+                ..State::initial()
+            };
+
+            let (placement, probability) = best_move(&CONFIG, &state).unwrap();
+
+            assert_eq!(probability, 1.0);
+            assert!(placement.is_hold_used);
+            assert_eq!(placement.piece.kind, PieceKind::I);
+        }
+
+        #[test]
+        fn holds_into_empty_hold_for_unknown_piece() {
+            let state = State {
+                game: Game {
+                    board: board_with_empty_columns(&[9]),
+                    piece: Some(Piece::spawn(&CONFIG, &PieceKind::O)),
+                    ..Game::initial()
+                },
+                // Holding the O piece deals one of the other six pieces in the bag.
+                piece_history: history_with_seen(&[PieceKind::O]),
+                moves_remaining: 1,
+            };
+
+            let (placement, probability) = best_move(&CONFIG, &state).unwrap();
+
+            assert!((probability - 1.0 / 6.0).abs() < 1e-6);
+            assert!(placement.is_hold_used);
+            assert_eq!(placement.piece.kind, PieceKind::I);
+            assert!((placement.probability - 1.0 / 6.0).abs() < 1e-6);
+            assert!(placement.board.can_perfect_clear());
+        }
+
+        #[test]
+        fn weighs_unknown_pieces_by_chance() {
+            let state = State {
+                game: Game {
+                    board: board_with_empty_columns(&[0, 1, 9]),
+                    piece: Some(Piece::spawn(&CONFIG, &PieceKind::I)),
+                    // The Z piece cannot help fill the empty columns.
+                    hold_kind: Some(PieceKind::Z),
+                    ..Game::initial()
+                },
+                // Only the O piece is left in the bag.
+                piece_history: history_with_seen(&[
+                    PieceKind::I,
+                    PieceKind::J,
+                    PieceKind::L,
+                    PieceKind::S,
+                    PieceKind::T,
+                    PieceKind::Z,
+                ]),
+                moves_remaining: 3,
+            };
+
+            let (placement, probability) = best_move(&CONFIG, &state).unwrap();
+
+            assert!((probability - 1.0 / 7.0).abs() < 1e-6);
+            assert_eq!(
+                placement.board,
+                board_with_empty_columns(&[0, 1]),
+                "Expected the I piece to fill the right column"
+            );
+        }
+    }
+
+    mod solver {
+        use super::*;
+
+        fn state_with_guessed_piece() -> State {
+            State {
+                game: Game {
+                    board: board_with_empty_columns(&[0, 1, 9]),
+                    piece: Some(Piece::spawn(&CONFIG, &PieceKind::I)),
+                    // The Z piece cannot help fill the empty columns.
+                    hold_kind: Some(PieceKind::Z),
+                    ..Game::initial()
+                },
+                piece_history: history_with_seen(&[
+                    PieceKind::I,
+                    PieceKind::J,
+                    PieceKind::L,
+                    PieceKind::S,
+                    PieceKind::T,
+                    PieceKind::Z,
+                ]),
+                moves_remaining: 3,
+            }
+        }
+
+        #[test]
+        fn reuses_probabilities_across_calls() {
+            let mut solver = Solver::new();
+            solver.update_state(state_with_guessed_piece());
+
+            let first = solver.best_move();
+            let table_len = solver.transposition_table.len();
+            let second = solver.best_move();
+
+            assert!(table_len > 0);
+            assert_eq!(solver.transposition_table.len(), table_len);
+            assert_eq!(first, second);
+            assert_eq!(first, best_move(&CONFIG, &state_with_guessed_piece()));
+        }
+
+        #[test]
+        fn clears_table_on_config_change() {
+            let mut solver = Solver::new();
+            solver.update_state(state_with_guessed_piece());
+            solver.best_move();
+
+            solver.update_config(Config::default());
+            assert!(!solver.transposition_table.is_empty());
+
+            let config = Config {
+                randomizer: Randomizer::Random,
+                ..Config::default()
+            };
+            solver.update_config(config.clone());
+            assert!(solver.transposition_table.is_empty());
+
+            let (_, probability) = solver.best_move().unwrap();
+            assert_eq!(
+                Some(probability),
+                best_move(&config, &state_with_guessed_piece()).map(|(_, p)| p)
+            );
         }
     }
 }
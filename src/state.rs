@@ -1,13 +1,19 @@
+use crate::board::Board;
 use crate::config::Config;
-use crate::game::{Action as GameAction, Game, ReduceError as GameError};
+use crate::game::{
+    Action as GameAction, Game, InitialActions, PlaceOutcome, ReduceError as GameError, SpawnError,
+};
 use crate::piece::{Piece, PieceKind};
+use crate::randomizer::{PieceHistory, PieceRandomizer};
 use crate::utils::piece_kind_set::PieceKindSet;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct State {
     pub game: Game,
 
-    pub seen_piece_kind_in_bag: PieceKindSet<bool>,
+    /// The piece kinds dealt up to and including the active piece. Pieces in the queue are added
+    /// to the history when they are consumed.
+    pub piece_history: PieceHistory,
 
     pub moves_remaining: u8,
 }
@@ -16,83 +22,154 @@ impl State {
     pub fn initial() -> State {
         State {
             game: Game::initial(),
-            seen_piece_kind_in_bag: PieceKindSet::new_with_value(false),
+            piece_history: PieceHistory::initial(),
             moves_remaining: 10,
         }
     }
 
     pub fn reduce(&self, config: &Config, action: &Action) -> Result<State, ReduceError> {
+        self.reduce_with_outcome(config, action)
+            .map(|(state, _)| state)
+    }
+
+    /// Reduce the state, along with the outcome of the placement if the action placed a piece.
+    pub fn reduce_with_outcome(
+        &self,
+        config: &Config,
+        action: &Action,
+    ) -> Result<(State, Option<PlaceOutcome>), ReduceError> {
         match action {
-            Action::ConsumeQueue => self
-                .with_consumed_queue(config)
-                .map_err(|e| ReduceError::ConsumeQueue(e)),
-            Action::WithNextPiece { kind } => self
-                .with_next_piece(config, kind)
-                .map_err(|e| ReduceError::ConsumeQueue(e)),
+            Action::ConsumeQueue { initial } => self
+                .with_consumed_queue(config, initial)
+                .map(|state| (state, None))
+                .map_err(ReduceError::ConsumeQueue),
+            Action::WithNextPiece { kind, initial } => self
+                .with_next_piece(config, kind, initial)
+                .map(|state| (state, None))
+                .map_err(ReduceError::ConsumeQueue),
             Action::Play(action) => self
                 .game
-                .reduce(config, action)
-                .map(|game| State {
-                    game,
-                    moves_remaining: if *action == GameAction::Place {
-                        self.moves_remaining - 1
-                    } else {
-                        self.moves_remaining
-                    },
-                    ..self.clone()
+                .reduce_with_outcome(config, action)
+                .map(|(game, outcome)| {
+                    let state = State {
+                        game,
+                        moves_remaining: if *action == GameAction::Place {
+                            self.moves_remaining - 1
+                        } else {
+                            self.moves_remaining
+                        },
+                        ..self.clone()
+                    };
+                    (state, outcome)
                 })
-                .map_err(|e| ReduceError::Play(e)),
+                .map_err(ReduceError::Play),
+        }
+    }
+
+    /// The state with as many moves remaining as pieces needed to fill the empty cells up to the
+    /// configured perfect clear height.
+    pub fn with_perfect_clear_budget(&self, config: &Config) -> State {
+        let empty_cell_count = self
+            .game
+            .board
+            .empty_cell_count(config.perfect_clear_height);
+        State {
+            moves_remaining: (empty_cell_count / 4) as u8,
+            ..self.clone()
+        }
+    }
+
+    /// Everything about the state which affects the perfect clears that can be reached from it.
+    pub fn key(&self) -> StateKey {
+        StateKey {
+            board: self.game.board,
+            piece: self.game.piece,
+            hold_kind: self.game.hold_kind,
+            is_hold_used: self.game.is_hold_used,
+            queue: self.game.queue,
+            piece_history: self.piece_history,
+            moves_remaining: self.moves_remaining,
         }
     }
 
-    fn with_consumed_queue(&self, config: &Config) -> Result<State, QueueError> {
+    /// The probability of each piece kind being dealt after the queue is consumed.
+    pub fn next_piece_probabilities(&self, config: &Config) -> PieceKindSet<f32> {
+        config
+            .randomizer
+            .next_piece_probabilities(&self.piece_history)
+    }
+
+    fn with_consumed_queue(
+        &self,
+        config: &Config,
+        initial: &InitialActions,
+    ) -> Result<State, QueueError> {
         let Some((Some(next_piece_kind), rest_piece_kinds)) = self.game.queue.split_first() else {
             return Err(QueueError::QueueEmpty);
         };
 
-        let next_piece = Piece::spawn(config, next_piece_kind);
-
-        if !self.game.board.can_fit(&next_piece.get_points(config)) {
-            return Err(QueueError::PieceCollision);
-        }
-
         let mut new_queue = [None; 7];
         new_queue[..rest_piece_kinds.len()].clone_from_slice(rest_piece_kinds);
 
-        let next_state = self.clone();
+        let game = Game {
+            queue: new_queue,
+            ..self.game.clone()
+        }
+        .with_spawned_piece(config, next_piece_kind, initial)
+        .map_err(QueueError::Spawn)?;
+
         Ok(State {
-            game: Game {
-                queue: new_queue,
-                piece: Some(next_piece),
-                is_hold_used: false,
-                ..next_state.game
-            },
-            ..next_state
+            game,
+            piece_history: config
+                .randomizer
+                .with_seen_piece_kind(&self.piece_history, next_piece_kind),
+            ..self.clone()
         })
     }
 
-    fn with_next_piece(&self, config: &Config, kind: &PieceKind) -> Result<State, QueueError> {
-        let next_piece = Piece::spawn(config, kind);
+    fn with_next_piece(
+        &self,
+        config: &Config,
+        kind: &PieceKind,
+        initial: &InitialActions,
+    ) -> Result<State, QueueError> {
+        let game = self
+            .game
+            .with_spawned_piece(config, kind, initial)
+            .map_err(QueueError::Spawn)?;
 
-        if !self.game.board.can_fit(&next_piece.get_points(config)) {
-            return Err(QueueError::PieceCollision);
-        }
-
-        let next_state = self.clone();
         Ok(State {
-            game: Game {
-                piece: Some(next_piece),
-                ..next_state.game
-            },
-            ..next_state
+            game,
+            piece_history: config
+                .randomizer
+                .with_seen_piece_kind(&self.piece_history, kind),
+            ..self.clone()
         })
     }
 }
 
+/// Identifies states which are equivalent to the solver, so that results can be shared between
+/// them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StateKey {
+    pub board: Board,
+    pub piece: Option<Piece>,
+    pub hold_kind: Option<PieceKind>,
+    pub is_hold_used: bool,
+    pub queue: [Option<PieceKind>; 7],
+    pub piece_history: PieceHistory,
+    pub moves_remaining: u8,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Action {
-    ConsumeQueue,
-    WithNextPiece { kind: PieceKind },
+    ConsumeQueue {
+        initial: InitialActions,
+    },
+    WithNextPiece {
+        kind: PieceKind,
+        initial: InitialActions,
+    },
     Play(GameAction),
 }
 
@@ -105,13 +182,16 @@ pub enum ReduceError {
 #[derive(Debug, PartialEq)]
 pub enum QueueError {
     QueueEmpty,
-    PieceCollision,
+    Spawn(SpawnError),
 }
 
 #[cfg(test)]
 mod tests {
     use crate::board::Board;
+    use crate::config::Spawn;
+    use crate::test_utils::queue_of;
     use crate::utils::point::Point;
+    use crate::utils::rotation::Orientation;
 
     use super::*;
 
@@ -126,7 +206,12 @@ mod tests {
         fn invalid_if_queue_empty() {
             let state = State::initial();
 
-            let next_state = state.reduce(&CONFIG, &Action::ConsumeQueue);
+            let next_state = state.reduce(
+                &CONFIG,
+                &Action::ConsumeQueue {
+                    initial: InitialActions::NONE,
+                },
+            );
 
             assert_eq!(
                 next_state,
@@ -139,7 +224,7 @@ mod tests {
         fn invalid_if_new_piece_intersects_board() {
             let mut board = Board::empty_board();
             for x in 3..7 {
-                board.fill(&Point::new(x, 2));
+                board.fill(&Point::new(x, 20));
             }
 
             let mut queue: [Option<PieceKind>; 7] = [None; 7];
@@ -154,11 +239,18 @@ mod tests {
                 ..State::initial()
             };
 
-            let next_state = state.reduce(&CONFIG, &Action::ConsumeQueue);
+            let next_state = state.reduce(
+                &CONFIG,
+                &Action::ConsumeQueue {
+                    initial: InitialActions::NONE,
+                },
+            );
 
             assert_eq!(
                 next_state,
-                Err(ReduceError::ConsumeQueue(QueueError::PieceCollision)),
+                Err(ReduceError::ConsumeQueue(QueueError::Spawn(
+                    SpawnError::PieceCollision
+                ))),
                 "Expected state to be invalid if next active piece intersects the board",
             )
         }
@@ -176,7 +268,12 @@ mod tests {
                 ..State::initial()
             };
 
-            let next_state = state.reduce(&CONFIG, &Action::ConsumeQueue);
+            let next_state = state.reduce(
+                &CONFIG,
+                &Action::ConsumeQueue {
+                    initial: InitialActions::NONE,
+                },
+            );
 
             assert!(next_state.is_ok());
 
@@ -187,7 +284,7 @@ mod tests {
 
         #[test]
         fn consumes_queue_and_sets_piece() {
-            let queue: [Option<PieceKind>; 7] = PIECE_KINDS.map(|k| Some(k));
+            let queue: [Option<PieceKind>; 7] = PIECE_KINDS.map(Some);
 
             let state = State {
                 game: Game {
@@ -197,7 +294,12 @@ mod tests {
                 ..State::initial()
             };
 
-            let next_state = state.reduce(&CONFIG, &Action::ConsumeQueue);
+            let next_state = state.reduce(
+                &CONFIG,
+                &Action::ConsumeQueue {
+                    initial: InitialActions::NONE,
+                },
+            );
 
             assert!(next_state.is_ok());
             let next_state = next_state.unwrap();
@@ -217,13 +319,22 @@ mod tests {
                 ]
             );
 
-            let next_state = next_state.reduce(&CONFIG, &Action::ConsumeQueue);
+            let next_state = next_state.reduce(
+                &CONFIG,
+                &Action::ConsumeQueue {
+                    initial: InitialActions::NONE,
+                },
+            );
 
             assert!(next_state.is_ok());
             let next_state = next_state.unwrap();
 
             assert!(next_state.game.piece.is_some());
             assert_eq!(next_state.game.piece.as_ref().unwrap().kind, PieceKind::J);
+            let seen = next_state.piece_history.seen_piece_kind_in_bag;
+            assert_eq!(seen.get(&PieceKind::I), 1);
+            assert_eq!(seen.get(&PieceKind::J), 1);
+            assert_eq!(seen.get(&PieceKind::L), 0);
             assert_eq!(
                 next_state.game.queue,
                 [
@@ -237,6 +348,119 @@ mod tests {
                 ]
             );
         }
+
+        #[test]
+        fn honours_spawn_rules() {
+            let config = Config {
+                spawn: Spawn {
+                    orientation: Orientation::West,
+                    above_stack: true,
+                    ..Spawn::default()
+                },
+                ..Config::default()
+            };
+            let mut board = Board::empty_board();
+            for x in 3..7 {
+                board.fill(&Point::new(x, 20));
+            }
+            let state = State {
+                game: Game {
+                    board,
+                    queue: queue_of(&[PieceKind::T]),
+                    ..State::initial().game
+                },
+                ..State::initial()
+            };
+
+            let next_state = state
+                .reduce(
+                    &config,
+                    &Action::ConsumeQueue {
+                        initial: InitialActions::NONE,
+                    },
+                )
+                .unwrap();
+
+            let piece = next_state.game.piece.unwrap();
+            assert_eq!(piece.orientation, Orientation::West);
+            assert!(piece.get_points(&config).iter().all(|point| point.y > 20));
+        }
+    }
+
+    mod with_initial_actions {
+        use crate::utils::rotation::Rotation;
+
+        use super::*;
+
+        #[test]
+        fn consumes_queue_with_initial_hold() {
+            let config = Config {
+                initial_rotation: true,
+                initial_hold: true,
+                ..Config::default()
+            };
+            let state = State {
+                game: Game {
+                    hold_kind: Some(PieceKind::I),
+                    queue: queue_of(&[PieceKind::T, PieceKind::O]),
+                    ..State::initial().game
+                },
+                ..State::initial()
+            };
+
+            let next_state = state
+                .reduce(
+                    &config,
+                    &Action::ConsumeQueue {
+                        initial: InitialActions {
+                            rotation: Some(Rotation::Clockwise),
+                            hold: true,
+                        },
+                    },
+                )
+                .unwrap();
+
+            let piece = next_state.game.piece.unwrap();
+            assert_eq!(piece.kind, PieceKind::I);
+            assert_eq!(piece.orientation, Orientation::East);
+            assert_eq!(next_state.game.hold_kind, Some(PieceKind::T));
+            assert_eq!(next_state.game.queue[0], Some(PieceKind::O));
+            assert_eq!(
+                next_state
+                    .piece_history
+                    .seen_piece_kind_in_bag
+                    .get(&PieceKind::T),
+                1
+            );
+        }
+
+        #[test]
+        fn invalid_if_not_allowed() {
+            let state = State {
+                game: Game {
+                    queue: queue_of(&[PieceKind::T]),
+                    ..State::initial().game
+                },
+                ..State::initial()
+            };
+
+            let next_state = state.reduce(
+                &CONFIG,
+                &Action::ConsumeQueue {
+                    initial: InitialActions {
+                        rotation: Some(Rotation::Clockwise),
+                        hold: false,
+                    },
+                },
+            );
+
+            assert_eq!(
+                next_state,
+                Err(ReduceError::ConsumeQueue(QueueError::Spawn(
+                    SpawnError::InitialRotationNotAllowed
+                )))
+            );
+        }
     }
 
     mod with_guessed_next {
@@ -246,7 +470,7 @@ mod tests {
         fn invalid_if_new_piece_intersects_board() {
             let mut board = Board::empty_board();
             for x in 3..7 {
-                board.fill(&Point::new(x, 2));
+                board.fill(&Point::new(x, 20));
             }
 
             let state = State {
@@ -257,11 +481,19 @@ mod tests {
                 ..State::initial()
             };
 
-            let next_state = state.reduce(&CONFIG, &Action::WithNextPiece { kind: PieceKind::I });
+            let next_state = state.reduce(
+                &CONFIG,
+                &Action::WithNextPiece {
+                    kind: PieceKind::I,
+                    initial: InitialActions::NONE,
+                },
+            );
 
             assert_eq!(
                 next_state,
-                Err(ReduceError::ConsumeQueue(QueueError::PieceCollision)),
+                Err(ReduceError::ConsumeQueue(QueueError::Spawn(
+                    SpawnError::PieceCollision
+                ))),
                 "Expected state to be invalid if next active piece intersects the board",
             )
         }
@@ -270,7 +502,13 @@ mod tests {
         fn updates_piece() {
             let state = State::initial();
 
-            let next_state = state.reduce(&CONFIG, &Action::WithNextPiece { kind: PieceKind::J });
+            let next_state = state.reduce(
+                &CONFIG,
+                &Action::WithNextPiece {
+                    kind: PieceKind::J,
+                    initial: InitialActions::NONE,
+                },
+            );
 
             assert!(next_state.is_ok());
             let next_state = next_state.unwrap();
@@ -278,5 +516,120 @@ mod tests {
             assert!(next_state.game.piece.is_some());
             assert_eq!(next_state.game.piece.as_ref().unwrap().kind, PieceKind::J);
         }
+
+        #[test]
+        fn marks_piece_kind_seen_in_bag() {
+            let state = State::initial();
+
+            let next_state = state.reduce(
+                &CONFIG,
+                &Action::WithNextPiece {
+                    kind: PieceKind::J,
+                    initial: InitialActions::NONE,
+                },
+            );
+
+            assert!(next_state.is_ok());
+            let next_state = next_state.unwrap();
+
+            assert_eq!(
+                next_state
+                    .piece_history
+                    .seen_piece_kind_in_bag
+                    .get(&PieceKind::J),
+                1
+            );
+            let probabilities = next_state.next_piece_probabilities(&CONFIG);
+            assert_eq!(probabilities.get(&PieceKind::J), 0.0);
+            assert_eq!(probabilities.get(&PieceKind::I), 1.0 / 6.0);
+        }
+    }
+
+    mod with_perfect_clear_budget {
+        use super::*;
+
+        #[test]
+        fn derives_moves_from_height() {
+            for (height, moves_remaining) in [(2, 5), (4, 10), (6, 15)] {
+                let config = Config {
+                    perfect_clear_height: height,
+                    ..Config::default()
+                };
+
+                let state = State::initial().with_perfect_clear_budget(&config);
+
+                assert_eq!(state.moves_remaining, moves_remaining);
+            }
+        }
+
+        #[test]
+        fn only_counts_empty_cells() {
+            let mut board = Board::empty_board();
+            for x in 0..8 {
+                board.fill(&Point::new(x, 0));
+            }
+            let state = State {
+                game: Game {
+                    board,
+                    ..Game::initial()
+                },
+                ..State::initial()
+            };
+
+            let state = state.with_perfect_clear_budget(&CONFIG);
+
+            assert_eq!(state.moves_remaining, 8);
+        }
+    }
+
+    mod key {
+        use crate::randomizer::PieceRandomizer;
+
+        use super::*;
+
+        #[test]
+        fn equal_for_equal_states() {
+            assert_eq!(State::initial().key(), State::initial().key());
+        }
+
+        #[test]
+        fn differs_by_hold_kind() {
+            let state = State {
+                game: Game {
+                    hold_kind: Some(PieceKind::I),
+                    ..Game::initial()
+                },
+                ..State::initial()
+            };
+
+            assert_ne!(state.key(), State::initial().key());
+        }
+
+        #[test]
+        fn differs_by_queue() {
+            let mut queue = [None; 7];
+            queue[0] = Some(PieceKind::T);
+            let state = State {
+                game: Game {
+                    queue,
+                    ..Game::initial()
+                },
+                ..State::initial()
+            };
+
+            assert_ne!(state.key(), State::initial().key());
+        }
+
+        #[test]
+        fn differs_by_piece_kinds_seen_in_bag() {
+            let state = State {
+                piece_history: CONFIG
+                    .randomizer
+                    .with_seen_piece_kind(&PieceHistory::initial(), &PieceKind::O),
+                ..State::initial()
+            };
+
+            assert_ne!(state.key(), State::initial().key());
+        }
     }
 }
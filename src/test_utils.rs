@@ -0,0 +1,40 @@
+use crate::board::Board;
+use crate::piece::PieceKind;
+use crate::randomizer::PieceHistory;
+use crate::utils::piece_kind_set::PieceKindSet;
+use crate::utils::point::Point;
+
+/// Fill the bottom 4 lines except for the given columns.
+pub fn board_with_empty_columns(columns: &[isize]) -> Board {
+    let mut board = Board::empty_board();
+    for y in 0..4 {
+        for x in 0..Board::WIDTH {
+            if !columns.contains(&x) {
+                board.fill(&Point::new(x, y));
+            }
+        }
+    }
+    board
+}
+
+/// A history where each of `kinds` has been seen in the current bag, once for every time it is
+/// listed.
+pub fn history_with_seen(kinds: &[PieceKind]) -> PieceHistory {
+    let mut seen = PieceKindSet::new_with_value(0);
+    for kind in kinds {
+        seen.set(kind, seen.get(kind) + 1);
+    }
+    PieceHistory {
+        seen_piece_kind_in_bag: seen,
+        ..PieceHistory::initial()
+    }
+}
+
+/// A queue of `kinds` in order, followed by empty slots.
+pub fn queue_of(kinds: &[PieceKind]) -> [Option<PieceKind>; 7] {
+    let mut queue = [None; 7];
+    for (idx, &kind) in kinds.iter().enumerate() {
+        queue[idx] = Some(kind);
+    }
+    queue
+}
@@ -1,6 +1,6 @@
 use crate::piece::PieceKind;
 
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
 pub struct PieceKindSet<V> {
     data: [V; 7],
 }
@@ -12,6 +12,10 @@ where
     pub fn get(&self, kind: &PieceKind) -> V {
         self.data[*kind as usize]
     }
+
+    pub fn set(&mut self, kind: &PieceKind, value: V) {
+        self.data[*kind as usize] = value;
+    }
 }
 
 impl<V> PieceKindSet<V>
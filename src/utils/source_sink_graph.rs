@@ -17,6 +17,12 @@ impl<N, E> SourceSinkGraph<N, E, Directed> {
     }
 }
 
+impl<N, E> Default for SourceSinkGraph<N, E, Directed> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<N, E> SourceSinkGraph<N, E, Undirected> {
     pub fn new_undirected() -> Self {
         SourceSinkGraph {
@@ -18,6 +18,12 @@ impl<N, E> WeightIndexedGraph<N, E, Directed> {
     }
 }
 
+impl<N, E> Default for WeightIndexedGraph<N, E, Directed> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<N, E> WeightIndexedGraph<N, E, Undirected> {
     pub fn new_undirected() -> Self {
         WeightIndexedGraph {